@@ -1,4 +1,4 @@
-mod vector; // Declare the module `vector`
+use vector_project::vector; // Use the `vector` module of the library
 
 fn main() {
     // Create a new mutable instance of a `Vector` from the `vector` module, initialized with a capacity of 0
@@ -11,5 +11,5 @@ fn main() {
     println!("Vector size: {}", vec.size());
 
     // Print the first element in the vector (at index 0)
-    println!("First lement: {}", vec.at(0));
+    println!("First lement: {}", vec[0]);
 }
@@ -0,0 +1,37 @@
+use vector_project::growth::{Doubling, FixedIncrement, GrowthPolicy, Hysteresis, NeverShrink, OneAndHalf};
+use vector_project::Vector;
+
+// Pushes 1000 items, pops all but 10 of them and prints how the capacity changed on the way
+fn trace<P: GrowthPolicy>(name: &str, policy: P) {
+    let mut vec = Vector::with_policy(0, policy);
+    let mut reallocations = 0;
+
+    let mut capacity = vec.capacity();
+    for i in 0..1000 {
+        vec.push(i);
+        if vec.capacity() != capacity {
+            reallocations += 1;
+            capacity = vec.capacity();
+        }
+    }
+    let peak = vec.capacity();
+
+    while vec.size() > 10 {
+        vec.pop();
+        if vec.capacity() != capacity {
+            reallocations += 1;
+            capacity = vec.capacity();
+        }
+    }
+
+    println!("{:<16} peak {:>5}, final {:>4}, reallocations {:>3}", name, peak, vec.capacity(), reallocations);
+}
+
+fn main() {
+    // Compare the built-in growth policies on the same workload
+    trace("Doubling", Doubling);
+    trace("OneAndHalf", OneAndHalf);
+    trace("FixedIncrement", FixedIncrement::new(64));
+    trace("NeverShrink", NeverShrink(Doubling));
+    trace("Hysteresis", Hysteresis::default());
+}
@@ -0,0 +1,357 @@
+// Allocators a `Vector` can take its buffer from, chosen with `Vector::new_in` and friends:
+// * Allocator - the trait: allocate(layout) returns a block or null, deallocate(ptr, layout) frees it
+// * Global - the global allocator behind `std::alloc`, used by every `Vector` unless told otherwise
+// * CountingAllocator - forwards to another allocator and counts allocations, frees and live bytes
+// * BumpAllocator - carves blocks out of one fixed-size arena and frees them all at once
+//
+// `CountingAllocator` and `BumpAllocator` are handles: clones share the same counters or arena, so a
+// cloned vector (or the vector returned by `splice`) is counted in, or allocated from, the same place.
+// A `&A` is an allocator too, for vectors that borrow an arena instead of sharing it. The sorts,
+// `freeze`, `into_sorted`, `PriorityQueue`, `HashMap` and `binary::write` work with any of them.
+
+use std::alloc::{alloc, dealloc, handle_alloc_error, Layout};
+use std::fmt;
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// Source of the memory behind a `Vector`'s buffer. It is unsafe to implement because `Vector`
+// trusts every block it gets: a non-null block must be aligned to and large enough for `layout`,
+// overlap no other live block and stay valid until it is passed to `deallocate` on this allocator
+// or one of its clones. `Vector` never asks for zero-sized blocks.
+#[allow(clippy::missing_safety_doc)] // The contract is the comment above
+pub unsafe trait Allocator {
+    // Allocates an uninitialized block for `layout`, or returns null if there is no memory for it;
+    // `Vector` turns null into `VectorError::AllocFailed`
+    fn allocate(&self, layout: Layout) -> *mut u8;
+
+    // Frees a block that `allocate` on this allocator or one of its clones returned for the same
+    // `layout` and that was not freed already
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout);
+}
+
+unsafe impl<A: Allocator + ?Sized> Allocator for &A {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+}
+
+// The global allocator, i.e. whatever `#[global_allocator]` the program uses
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Global;
+
+unsafe impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        unsafe { alloc(layout) }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        dealloc(ptr, layout)
+    }
+}
+
+// Counters shared by every clone of a `CountingAllocator`
+#[derive(Default)]
+struct Counts {
+    allocations: AtomicUsize,
+    deallocations: AtomicUsize,
+    bytes_allocated: AtomicUsize,
+    bytes_freed: AtomicUsize,
+}
+
+// Allocator that forwards to `inner` and counts what passes through it; failed allocations are not counted
+pub struct CountingAllocator<A = Global> {
+    inner: A,            // Allocator doing the actual work
+    counts: Arc<Counts>, // Shared with every clone
+}
+
+impl CountingAllocator {
+    // Creates a counting allocator on top of the global allocator, with every counter at zero
+    pub fn new() -> Self {
+        Self::wrapping(Global)
+    }
+}
+
+impl<A: Allocator> CountingAllocator<A> {
+    // Creates a counting allocator on top of `inner`, with every counter at zero
+    pub fn wrapping(inner: A) -> Self {
+        CountingAllocator { inner, counts: Arc::default() }
+    }
+
+    // Returns the allocator doing the actual work
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    // Returns the number of blocks allocated
+    pub fn allocations(&self) -> usize {
+        self.counts.allocations.load(Ordering::Relaxed)
+    }
+
+    // Returns the number of blocks freed
+    pub fn deallocations(&self) -> usize {
+        self.counts.deallocations.load(Ordering::Relaxed)
+    }
+
+    // Returns the total size of the blocks allocated
+    pub fn bytes_allocated(&self) -> usize {
+        self.counts.bytes_allocated.load(Ordering::Relaxed)
+    }
+
+    // Returns the number of bytes allocated and not yet freed
+    pub fn live_bytes(&self) -> usize {
+        self.bytes_allocated() - self.counts.bytes_freed.load(Ordering::Relaxed)
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for CountingAllocator<A> {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.allocate(layout);
+        if !ptr.is_null() {
+            self.counts.allocations.fetch_add(1, Ordering::Relaxed);
+            self.counts.bytes_allocated.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        // Count first, so the counters never show more bytes freed than allocated
+        self.counts.deallocations.fetch_add(1, Ordering::Relaxed);
+        self.counts.bytes_freed.fetch_add(layout.size(), Ordering::Relaxed);
+        self.inner.deallocate(ptr, layout)
+    }
+}
+
+// Clones share the counters
+impl<A: Clone> Clone for CountingAllocator<A> {
+    fn clone(&self) -> Self {
+        CountingAllocator { inner: self.inner.clone(), counts: Arc::clone(&self.counts) }
+    }
+}
+
+impl<A: Allocator + Default> Default for CountingAllocator<A> {
+    fn default() -> Self {
+        Self::wrapping(A::default())
+    }
+}
+
+impl<A: Allocator> fmt::Debug for CountingAllocator<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CountingAllocator")
+            .field("allocations", &self.allocations())
+            .field("deallocations", &self.deallocations())
+            .field("live_bytes", &self.live_bytes())
+            .finish()
+    }
+}
+
+// Size of the arena behind `BumpAllocator::default()`
+pub const DEFAULT_ARENA_SIZE: usize = 1 << 20;
+
+// Alignment of the arena itself; blocks with a larger alignment are placed further in
+const ARENA_ALIGN: usize = 16;
+
+// Memory shared by every clone of a `BumpAllocator`, freed when the last one is dropped
+struct Arena {
+    base: *mut u8,     // Start of the arena
+    capacity: usize,   // Size of the arena in bytes
+    used: AtomicUsize, // Offset of the first byte not handed out yet
+}
+
+// The arena is only written through blocks handed out exactly once, and `used` is atomic
+unsafe impl Send for Arena {}
+unsafe impl Sync for Arena {}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        if self.capacity > 0 {
+            unsafe { dealloc(self.base, Layout::from_size_align(self.capacity, ARENA_ALIGN).unwrap()) };
+        }
+    }
+}
+
+// Allocator that hands out consecutive blocks of one arena; freeing a block only gives memory back
+// if it is the most recent one, everything else is reclaimed when the arena goes away or by `reset`.
+// Vectors that grow step by step leave their old buffers behind, so size the arena for the total.
+pub struct BumpAllocator {
+    arena: Arc<Arena>, // Shared with every clone
+}
+
+impl BumpAllocator {
+    // Creates an allocator over a new arena of `capacity` bytes taken from the global allocator,
+    // blows up if the arena cannot be allocated
+    pub fn new(capacity: usize) -> Self {
+        let layout = Layout::from_size_align(capacity, ARENA_ALIGN).expect("Arena too large");
+        let base = if capacity == 0 {
+            NonNull::dangling().as_ptr()
+        } else {
+            let base = unsafe { alloc(layout) };
+            if base.is_null() {
+                handle_alloc_error(layout);
+            }
+            base
+        };
+        BumpAllocator { arena: Arc::new(Arena { base, capacity, used: AtomicUsize::new(0) }) }
+    }
+
+    // Returns the size of the arena in bytes
+    pub fn capacity(&self) -> usize {
+        self.arena.capacity
+    }
+
+    // Returns the number of bytes handed out, including alignment padding
+    pub fn used(&self) -> usize {
+        self.arena.used.load(Ordering::Relaxed)
+    }
+
+    // Returns the number of bytes still available
+    pub fn remaining(&self) -> usize {
+        self.capacity() - self.used()
+    }
+
+    // Makes the whole arena available again; does nothing and returns false while a clone, and so
+    // possibly a vector, still uses the arena
+    pub fn reset(&mut self) -> bool {
+        match Arc::get_mut(&mut self.arena) {
+            Some(arena) => {
+                *arena.used.get_mut() = 0;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+unsafe impl Allocator for BumpAllocator {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        let base = self.arena.base as usize;
+        let mut used = self.used();
+        loop {
+            // Place the block at the first suitably aligned address after the blocks handed out so far
+            let start = match (base + used).checked_next_multiple_of(layout.align()) {
+                Some(address) => address - base,
+                None => return ptr::null_mut(),
+            };
+            let end = match start.checked_add(layout.size()) {
+                Some(end) if end <= self.arena.capacity => end,
+                _ => return ptr::null_mut(),
+            };
+            match self.arena.used.compare_exchange_weak(used, end, Ordering::Relaxed, Ordering::Relaxed) {
+                Ok(_) => return unsafe { self.arena.base.add(start) },
+                Err(current) => used = current,
+            }
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        // Only the most recent block can be given back; if another block was handed out meanwhile,
+        // this one stays allocated until the arena is reset
+        let start = ptr as usize - self.arena.base as usize;
+        let _ = self.arena.used.compare_exchange(start + layout.size(), start, Ordering::Relaxed, Ordering::Relaxed);
+    }
+}
+
+// Clones share the arena
+impl Clone for BumpAllocator {
+    fn clone(&self) -> Self {
+        BumpAllocator { arena: Arc::clone(&self.arena) }
+    }
+}
+
+// A new arena of `DEFAULT_ARENA_SIZE` bytes
+impl Default for BumpAllocator {
+    fn default() -> Self {
+        Self::new(DEFAULT_ARENA_SIZE)
+    }
+}
+
+impl fmt::Debug for BumpAllocator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BumpAllocator").field("capacity", &self.capacity()).field("used", &self.used()).finish()
+    }
+}
+
+// Unit tests for the allocators; `vector` runs its whole test suite on top of each of them
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{Vector, VectorError};
+
+    #[test]
+    fn test_counting_allocator() {
+        let counter = CountingAllocator::new();
+        {
+            let mut vec = Vector::new_in(0, counter.clone());
+            for i in 0..100u64 {
+                vec.push(i);
+            }
+            assert_eq!(counter.allocations(), 4); // 16 -> 32 -> 64 -> 128
+            assert_eq!(counter.deallocations(), 3);
+            assert_eq!(counter.live_bytes(), 128 * 8);
+
+            // A clone of the vector allocates through the same counters
+            let copy = vec.clone();
+            assert_eq!(counter.allocations(), 5);
+            drop(copy);
+        }
+        assert_eq!(counter.deallocations(), 5);
+        assert_eq!(counter.bytes_allocated(), (16 + 32 + 64 + 128 + 128) * 8);
+        assert_eq!(counter.live_bytes(), 0);
+
+        // Zero-sized elements never reach the allocator
+        let mut units = Vector::new_in(0, &counter);
+        units.extend((0..100).map(|_| ()));
+        assert_eq!(counter.allocations(), 5);
+    }
+
+    #[test]
+    fn test_bump_allocator() {
+        let mut arena = BumpAllocator::new(4096);
+        {
+            let mut vec = Vector::new_in(0, &arena);
+            for i in 0..40u64 {
+                vec.push(i);
+            }
+            // 16 and 32 elements left behind, 64 elements in use
+            assert_eq!(arena.used(), (16 + 32 + 64) * 8);
+            assert!(vec.iter().copied().eq(0..40));
+
+            // Blocks respect the requested alignment
+            let block = arena.allocate(Layout::from_size_align(1, 256).unwrap());
+            assert_eq!(block as usize % 256, 0);
+        }
+        assert!(arena.reset());
+        assert_eq!(arena.used(), 0);
+
+        // Freeing the most recent block gives its memory back
+        let layout = Layout::array::<u32>(16).unwrap();
+        let block = arena.allocate(layout);
+        unsafe { arena.deallocate(block, layout) };
+        assert_eq!(arena.used(), 0);
+
+        // A clone keeps the arena alive and blocks resets
+        let shared = arena.clone();
+        assert!(!arena.reset());
+        drop(shared);
+        assert!(arena.reset());
+    }
+
+    #[test]
+    fn test_exhausted_arena_is_reported() {
+        let arena = BumpAllocator::new(256);
+        let mut vec: Vector<u32, _, _> = Vector::try_with_capacity_in(0, arena.clone()).unwrap();
+        vec.extend(0..16);
+        assert_eq!(arena.remaining(), 256 - 64);
+
+        // Growing to 32 elements needs 128 more bytes, which fit; 64 elements do not
+        assert_eq!(vec.try_reserve(16), Ok(()));
+        let layout = Layout::array::<u32>(64).unwrap();
+        assert_eq!(vec.try_reserve(32), Err(VectorError::AllocFailed { layout }));
+        assert_eq!(vec.capacity(), 32);
+        assert!(vec.iter().copied().eq(0..16));
+    }
+}
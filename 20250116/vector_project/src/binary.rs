@@ -0,0 +1,376 @@
+// Compact, self-describing binary format for `Vector<i32>`:
+// * write(vec, writer) - writes a vector to any `io::Write`
+// * read(reader) - reads a vector back from any `io::Read`, returning a FormatError instead of panicking
+//
+// Layout (all multi-byte integers little-endian):
+//     magic     4 bytes   b"VECB"
+//     version   1 byte    currently 1
+//     count     varint    number of elements
+//     payload   varints   each element zigzag-encoded, so small negative numbers stay short
+//     checksum  4 bytes   CRC-32 (IEEE) of every byte before it
+//
+// A varint stores 7 bits per byte, least significant group first, with the high bit set on every
+// byte except the last. Neither function buffers: wrap files and sockets in `BufReader`/`BufWriter`,
+// and `read` never consumes bytes past the checksum, so several vectors can share one stream.
+
+use crate::allocator::Allocator;
+use crate::growth::GrowthPolicy;
+use crate::vector::{Vector, VectorError};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+// Bytes every encoded vector starts with
+pub const MAGIC: [u8; 4] = *b"VECB";
+
+// Version written by `write` and the only one `read` accepts
+pub const VERSION: u8 = 1;
+
+// Largest number of elements `read` reserves room for before they have actually been read,
+// so that a corrupt count cannot trigger a huge allocation
+const MAX_PREALLOCATION: usize = 64 * 1024;
+
+// Errors returned by `read` and `write`
+#[derive(Debug)]
+pub enum FormatError {
+    // The underlying reader or writer failed
+    Io(io::Error),
+    // The input ended before the vector was complete
+    Truncated,
+    // The input does not start with `MAGIC`
+    BadMagic { found: [u8; 4] },
+    // The input was written in a version this code cannot read
+    UnsupportedVersion { found: u8 },
+    // A varint is longer than its type allows
+    MalformedVarint,
+    // The stored checksum does not match the bytes read
+    ChecksumMismatch { expected: u32, found: u32 },
+    // The vector could not hold the decoded elements
+    Vector(VectorError),
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Io(err) => write!(f, "I/O error: {}", err),
+            FormatError::Truncated => write!(f, "Input ended before the vector was complete"),
+            FormatError::BadMagic { found } => write!(f, "Not an encoded vector: bad magic {:02x?}", found),
+            FormatError::UnsupportedVersion { found } => {
+                write!(f, "Unsupported format version {} (expected {})", found, VERSION)
+            }
+            FormatError::MalformedVarint => write!(f, "Malformed varint"),
+            FormatError::ChecksumMismatch { expected, found } => {
+                write!(f, "Checksum mismatch: stored {:08x} but computed {:08x}", expected, found)
+            }
+            FormatError::Vector(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for FormatError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FormatError::Io(err) => Some(err),
+            FormatError::Vector(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Running out of input is reported as truncation rather than as a generic I/O error
+impl From<io::Error> for FormatError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            FormatError::Truncated
+        } else {
+            FormatError::Io(err)
+        }
+    }
+}
+
+impl From<VectorError> for FormatError {
+    fn from(err: VectorError) -> Self {
+        FormatError::Vector(err)
+    }
+}
+
+// Writes `vec` to `writer` in the format described above
+pub fn write<P: GrowthPolicy, A: Allocator, W: Write>(vec: &Vector<i32, P, A>, writer: W) -> Result<(), FormatError> {
+    let mut writer = Crc32Writer { inner: writer, crc: Crc32::new() };
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&[VERSION])?;
+    write_varint(&mut writer, vec.size() as u64)?;
+    for &item in vec.iter() {
+        write_varint(&mut writer, zigzag_encode(item) as u64)?;
+    }
+
+    let checksum = writer.crc.finish();
+    writer.inner.write_all(&checksum.to_le_bytes())?;
+    Ok(())
+}
+
+// Reads a vector written by `write` from `reader`, stopping right after its checksum
+pub fn read<R: Read>(reader: R) -> Result<Vector<i32>, FormatError> {
+    let mut reader = Crc32Reader { inner: reader, crc: Crc32::new() };
+
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != MAGIC {
+        return Err(FormatError::BadMagic { found: magic });
+    }
+    let mut version = [0; 1];
+    reader.read_exact(&mut version)?;
+    if version[0] != VERSION {
+        return Err(FormatError::UnsupportedVersion { found: version[0] });
+    }
+
+    let count = read_varint(&mut reader)?;
+    let count = usize::try_from(count).map_err(|_| VectorError::CapacityOverflow)?;
+    let mut vec = Vector::try_with_capacity(0)?;
+    vec.try_reserve(count.min(MAX_PREALLOCATION))?;
+    for _ in 0..count {
+        let value = read_varint(&mut reader)?;
+        let value = u32::try_from(value).map_err(|_| FormatError::MalformedVarint)?;
+        vec.try_push(zigzag_decode(value))?;
+    }
+
+    let computed = reader.crc.finish();
+    let mut stored = [0; 4];
+    reader.inner.read_exact(&mut stored)?;
+    let stored = u32::from_le_bytes(stored);
+    if stored != computed {
+        return Err(FormatError::ChecksumMismatch { expected: stored, found: computed });
+    }
+    Ok(vec)
+}
+
+// Maps signed integers to unsigned ones so that values close to zero have few significant bits:
+// 0 -> 0, -1 -> 1, 1 -> 2, -2 -> 3, ...
+pub(crate) fn zigzag_encode(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+// Reverses `zigzag_encode`
+pub(crate) fn zigzag_decode(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+// Writes `value` as a varint of at most 10 bytes
+pub(crate) fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buffer = [0; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer[len] = byte;
+            len += 1;
+            break;
+        }
+        buffer[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buffer[..len])
+}
+
+// Reads a varint written by `write_varint`, rejecting encodings that do not fit in 64 bits
+pub(crate) fn read_varint<R: Read>(reader: &mut R) -> Result<u64, FormatError> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+        let bits = (byte[0] & 0x7f) as u64;
+        if shift == 63 && bits > 1 {
+            return Err(FormatError::MalformedVarint);
+        }
+        value |= bits << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(FormatError::MalformedVarint)
+}
+
+// Lookup table for the reflected IEEE CRC-32 polynomial, built at compile time
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+// Incremental CRC-32 (IEEE), the checksum used by zip, gzip and PNG
+pub(crate) struct Crc32 {
+    state: u32,
+}
+
+impl Crc32 {
+    pub(crate) fn new() -> Self {
+        Crc32 { state: !0 }
+    }
+
+    pub(crate) fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state = CRC32_TABLE[((self.state ^ byte as u32) & 0xff) as usize] ^ (self.state >> 8);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u32 {
+        !self.state
+    }
+}
+
+// Writer that checksums everything written through it
+struct Crc32Writer<W> {
+    inner: W,
+    crc: Crc32,
+}
+
+impl<W: Write> Write for Crc32Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.crc.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+// Reader that checksums everything read through it
+struct Crc32Reader<R> {
+    inner: R,
+    crc: Crc32,
+}
+
+impl<R: Read> Read for Crc32Reader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.crc.update(&buf[..read]);
+        Ok(read)
+    }
+}
+
+// Unit tests for the binary format
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocator::BumpAllocator;
+    use crate::vector;
+
+    fn encode(vec: &Vector<i32>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write(vec, &mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_known_encoding() {
+        let bytes = encode(&vector![0, -1, 1, 300]);
+        let mut expected = b"VECB".to_vec();
+        expected.extend([1, 4, 0x00, 0x01, 0x02, 0xd8, 0x04]);
+        let checksum = {
+            let mut crc = Crc32::new();
+            crc.update(&expected);
+            crc.finish()
+        };
+        expected.extend(checksum.to_le_bytes());
+        assert_eq!(bytes, expected);
+
+        // Check value of the standard CRC-32 test vector
+        let mut crc = Crc32::new();
+        crc.update(b"123456789");
+        assert_eq!(crc.finish(), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let vec: Vector<i32> = [0, 1, -1, 63, -64, 64, i32::MAX, i32::MIN].into_iter().chain(-1000..1000).collect();
+        let bytes = encode(&vec);
+        assert_eq!(read(&bytes[..]).unwrap(), vec);
+
+        // The encoding does not depend on where the vector keeps its elements
+        let mut bumped = Vector::new_in(0, BumpAllocator::default());
+        bumped.extend_from_slice(&vec);
+        let mut bumped_bytes = Vec::new();
+        write(&bumped, &mut bumped_bytes).unwrap();
+        assert_eq!(bumped_bytes, bytes);
+
+        let empty = vector![];
+        assert!(read(&encode(&empty)[..]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reads_stop_after_checksum() {
+        let mut bytes = encode(&vector![1, 2]);
+        bytes.extend(encode(&vector![3]));
+        let mut reader = &bytes[..];
+        assert_eq!(read(&mut reader).unwrap(), [1, 2]);
+        assert_eq!(read(&mut reader).unwrap(), [3]);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_truncation_at_every_offset() {
+        let bytes = encode(&vector![5, -300, 70000, i32::MIN]);
+        for len in 0..bytes.len() {
+            assert!(matches!(read(&bytes[..len]), Err(FormatError::Truncated)), "length {}", len);
+        }
+    }
+
+    #[test]
+    fn test_header_errors() {
+        let mut bytes = encode(&vector![1]);
+        bytes[4] = 2;
+        assert!(matches!(read(&bytes[..]), Err(FormatError::UnsupportedVersion { found: 2 })));
+        bytes[0] = b'X';
+        assert!(matches!(read(&bytes[..]), Err(FormatError::BadMagic { .. })));
+    }
+
+    #[test]
+    fn test_corruption_is_detected() {
+        let bytes = encode(&vector![1, 2, 3, 4]);
+        for i in 5..bytes.len() {
+            let mut corrupt = bytes.clone();
+            corrupt[i] ^= 0x01;
+            assert!(read(&corrupt[..]).is_err(), "flipped byte {}", i);
+        }
+
+        let mut corrupt = bytes.clone();
+        corrupt[8] = 3;
+        assert!(matches!(read(&corrupt[..]), Err(FormatError::ChecksumMismatch { .. })));
+    }
+
+    #[test]
+    fn test_malformed_varints() {
+        // Values that do not fit in 32 bits, and varints longer than 64 bits
+        let mut bytes = b"VECB".to_vec();
+        bytes.extend([1, 1, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        assert!(matches!(read(&bytes[..]), Err(FormatError::MalformedVarint)));
+
+        let mut bytes = b"VECB".to_vec();
+        bytes.push(1);
+        bytes.extend([0xff; 10]);
+        assert!(matches!(read(&bytes[..]), Err(FormatError::MalformedVarint)));
+    }
+
+    #[test]
+    fn test_huge_count_does_not_preallocate() {
+        // A corrupt count claiming billions of elements fails on the missing data, not on allocation
+        let mut bytes = b"VECB".to_vec();
+        bytes.push(1);
+        write_varint(&mut bytes, u32::MAX as u64).unwrap();
+        bytes.push(0);
+        assert!(matches!(read(&bytes[..]), Err(FormatError::Truncated)));
+    }
+}
@@ -0,0 +1,457 @@
+// Compact vector of booleans, packing 64 bits into each word of a `Vector<u64>`:
+// * push(bit), pop(), insert(index, bit), delete(index) - like `Vector`, shifting whole words at a time
+// * get(index), at(index), set(index, bit)
+// * count_ones(), count_zeros()
+// * rank(index) - number of set bits before an index
+// * select(n) - index of the n-th set bit (counting from 0)
+// * iter(), iter_ones() - every bit, or the indices of set bits found with `trailing_zeros`
+// * &a & &b, &a | &b, &a ^ &b, !&a and the assigning forms - bitwise operations on equal-length vectors
+//
+// Growth follows `Vector`'s rules, since the words live in a `Vector`: the capacity is a power of
+// two words, at least 16 (1024 bits). Bits past the length in the last word are always zero.
+
+use crate::vector::{Vector, VectorError};
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not};
+
+// Number of bits in a word
+const WORD_BITS: usize = u64::BITS as usize;
+
+// Define a `BitVector` struct with the packed words and the number of bits
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct BitVector {
+    words: Vector<u64>, // Bit i lives in word i / 64 at position i % 64; exactly as many words as needed
+    len: usize,         // Number of bits
+}
+
+impl BitVector {
+    // Creates a new `BitVector` with room for at least `initial_capacity` bits
+    pub fn new(initial_capacity: usize) -> Self {
+        BitVector { words: Vector::new(initial_capacity.div_ceil(WORD_BITS)), len: 0 }
+    }
+
+    // Creates a new `BitVector` holding `len` copies of `bit`
+    pub fn from_elem(bit: bool, len: usize) -> Self {
+        let fill = if bit { u64::MAX } else { 0 };
+        let mut bits = BitVector { words: Vector::from_elem(fill, len.div_ceil(WORD_BITS)), len };
+        bits.clear_unused();
+        bits
+    }
+
+    // Returns the number of bits
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    // Returns the number of bits the vector can hold without resizing
+    pub fn capacity(&self) -> usize {
+        self.words.capacity() * WORD_BITS
+    }
+
+    // Checks if the vector holds no bits
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Returns the bit at a given index, blows up if index out of bounds
+    pub fn at(&self, index: usize) -> bool {
+        match self.get(index) {
+            Some(bit) => bit,
+            None => VectorError::IndexOutOfBounds { index, len: self.len }.raise(),
+        }
+    }
+
+    // Returns the bit at a given index, or `None` if the index is out of bounds
+    pub fn get(&self, index: usize) -> Option<bool> {
+        if index >= self.len {
+            return None;
+        }
+        Some(self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1)
+    }
+
+    // Sets the bit at a given index, blows up if index out of bounds
+    pub fn set(&mut self, index: usize, bit: bool) {
+        self.check_index(index);
+        let (word, mask) = (index / WORD_BITS, 1 << (index % WORD_BITS));
+        if bit {
+            self.words[word] |= mask;
+        } else {
+            self.words[word] &= !mask;
+        }
+    }
+
+    // Adds a bit at the end
+    pub fn push(&mut self, bit: bool) {
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.push(0);
+        }
+        self.len += 1;
+        self.set(self.len - 1, bit);
+    }
+
+    // Removes the last bit and returns it, `None` if empty
+    pub fn pop(&mut self) -> Option<bool> {
+        let bit = self.get(self.len.checked_sub(1)?)?;
+        self.set(self.len - 1, false);
+        self.len -= 1;
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.pop();
+        }
+        Some(bit)
+    }
+
+    // Inserts a bit at an index, shifting that bit and the trailing ones right; `index` may be the
+    // length, which appends. Blows up if index out of bounds.
+    pub fn insert(&mut self, index: usize, bit: bool) {
+        if index > self.len {
+            VectorError::IndexOutOfBounds { index, len: self.len }.raise();
+        }
+        self.push(false);
+
+        // Words after the insertion point move up by one bit, taking the top bit of the word before
+        let (word, offset) = (index / WORD_BITS, index % WORD_BITS);
+        for k in (word + 1..self.words.size()).rev() {
+            self.words[k] = self.words[k] << 1 | self.words[k - 1] >> (WORD_BITS - 1);
+        }
+        let below = low_mask(offset);
+        let current = self.words[word];
+        self.words[word] = current & below | (current & !below) << 1 | (bit as u64) << offset;
+    }
+
+    // Deletes the bit at an index, shifting the trailing bits left, and returns it; blows up if
+    // index out of bounds
+    pub fn delete(&mut self, index: usize) -> bool {
+        let bit = self.at(index);
+
+        // Words from the deleted bit on move down by one bit, taking the bottom bit of the word after
+        let (word, offset) = (index / WORD_BITS, index % WORD_BITS);
+        let words = self.words.size();
+        for k in word..words {
+            let carry = if k + 1 < words { self.words[k + 1] & 1 } else { 0 };
+            let current = self.words[k];
+            let shifted = if k == word {
+                let below = low_mask(offset);
+                current & below | current >> 1 & !below
+            } else {
+                current >> 1
+            };
+            self.words[k] = shifted | carry << (WORD_BITS - 1);
+        }
+        self.len -= 1;
+        if self.len.is_multiple_of(WORD_BITS) {
+            self.words.pop();
+        }
+        bit
+    }
+
+    // Returns the number of set bits
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    // Returns the number of unset bits
+    pub fn count_zeros(&self) -> usize {
+        self.len - self.count_ones()
+    }
+
+    // Returns the number of set bits before `index`, which may be the length; blows up if it is past it
+    pub fn rank(&self, index: usize) -> usize {
+        if index > self.len {
+            VectorError::IndexOutOfBounds { index, len: self.len }.raise();
+        }
+        let (word, offset) = (index / WORD_BITS, index % WORD_BITS);
+        let full: usize = self.words[..word].iter().map(|word| word.count_ones() as usize).sum();
+        let partial = if offset > 0 { (self.words[word] & low_mask(offset)).count_ones() as usize } else { 0 };
+        full + partial
+    }
+
+    // Returns the index of the `n`-th set bit, counting from 0, or `None` if there are not that many
+    pub fn select(&self, mut n: usize) -> Option<usize> {
+        for (i, &word) in self.words.iter().enumerate() {
+            let ones = word.count_ones() as usize;
+            if n < ones {
+                // Clear the lowest `n` set bits; the answer is then the lowest remaining one
+                let mut word = word;
+                for _ in 0..n {
+                    word &= word - 1;
+                }
+                return Some(i * WORD_BITS + word.trailing_zeros() as usize);
+            }
+            n -= ones;
+        }
+        None
+    }
+
+    // Returns an iterator over every bit
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = bool> + ExactSizeIterator + '_ {
+        (0..self.len).map(|index| self.words[index / WORD_BITS] >> (index % WORD_BITS) & 1 == 1)
+    }
+
+    // Returns an iterator over the indices of the set bits, in ascending order
+    pub fn iter_ones(&self) -> Ones<'_> {
+        Ones { words: self.words.as_slice(), index: 0, current: self.words.get(0).copied().unwrap_or(0) }
+    }
+
+    // Blows up if the index is out of bounds
+    fn check_index(&self, index: usize) {
+        if index >= self.len {
+            VectorError::IndexOutOfBounds { index, len: self.len }.raise();
+        }
+    }
+
+    // Zeroes the bits past the length in the last word
+    fn clear_unused(&mut self) {
+        let offset = self.len % WORD_BITS;
+        if offset > 0 {
+            let last = self.words.size() - 1;
+            self.words[last] &= low_mask(offset);
+        }
+    }
+
+    // Combines every word with the matching word of another vector of the same length
+    fn combine(&mut self, other: &BitVector, op: impl Fn(u64, u64) -> u64) {
+        assert_eq!(self.len, other.len, "Bitwise operation on BitVectors of different lengths");
+        for (word, &other) in self.words.iter_mut().zip(other.words.iter()) {
+            *word = op(*word, other);
+        }
+    }
+}
+
+// Returns a word with the lowest `bits` bits set; `bits` is below 64
+fn low_mask(bits: usize) -> u64 {
+    (1 << bits) - 1
+}
+
+// Define an iterator over the indices of the set bits of a `BitVector`
+pub struct Ones<'a> {
+    words: &'a [u64],
+    index: usize, // Index of the word being scanned
+    current: u64, // Bits of that word not yet returned
+}
+
+impl Iterator for Ones<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.index += 1;
+            self.current = *self.words.get(self.index)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.index * WORD_BITS + bit)
+    }
+}
+
+impl FusedIterator for Ones<'_> {}
+
+impl fmt::Debug for BitVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BitVector[")?;
+        for bit in self.iter() {
+            f.write_str(if bit { "1" } else { "0" })?;
+        }
+        f.write_str("]")
+    }
+}
+
+impl FromIterator<bool> for BitVector {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut bits = BitVector::new(iter.size_hint().0);
+        bits.extend(iter);
+        bits
+    }
+}
+
+impl Extend<bool> for BitVector {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        for bit in iter {
+            self.push(bit);
+        }
+    }
+}
+
+impl BitAndAssign<&BitVector> for BitVector {
+    fn bitand_assign(&mut self, other: &BitVector) {
+        self.combine(other, |a, b| a & b);
+    }
+}
+
+impl BitOrAssign<&BitVector> for BitVector {
+    fn bitor_assign(&mut self, other: &BitVector) {
+        self.combine(other, |a, b| a | b);
+    }
+}
+
+impl BitXorAssign<&BitVector> for BitVector {
+    fn bitxor_assign(&mut self, other: &BitVector) {
+        self.combine(other, |a, b| a ^ b);
+    }
+}
+
+impl BitAnd for &BitVector {
+    type Output = BitVector;
+
+    fn bitand(self, other: &BitVector) -> BitVector {
+        let mut result = self.clone();
+        result &= other;
+        result
+    }
+}
+
+impl BitOr for &BitVector {
+    type Output = BitVector;
+
+    fn bitor(self, other: &BitVector) -> BitVector {
+        let mut result = self.clone();
+        result |= other;
+        result
+    }
+}
+
+impl BitXor for &BitVector {
+    type Output = BitVector;
+
+    fn bitxor(self, other: &BitVector) -> BitVector {
+        let mut result = self.clone();
+        result ^= other;
+        result
+    }
+}
+
+impl Not for &BitVector {
+    type Output = BitVector;
+
+    fn not(self) -> BitVector {
+        let mut result = self.clone();
+        for word in result.words.iter_mut() {
+            *word = !*word;
+        }
+        result.clear_unused();
+        result
+    }
+}
+
+// Unit tests for the `BitVector` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn random_bits(seed: u32, len: usize) -> Vec<bool> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                state >> 16 & 3 == 0
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_push_get_set_pop() {
+        let mut bits = BitVector::new(0);
+        assert!(bits.is_empty());
+        assert_eq!(bits.capacity(), 1024);
+        for i in 0..200 {
+            bits.push(i % 3 == 0);
+        }
+        assert_eq!(bits.size(), 200);
+        assert!(bits.at(99));
+        assert_eq!(bits.get(100), Some(false));
+        assert_eq!(bits.get(200), None);
+        bits.set(100, true);
+        assert!(bits.at(100));
+        assert_eq!(bits.count_ones(), 68);
+        assert_eq!(bits.count_zeros(), 132);
+        for i in (0..200).rev() {
+            assert_eq!(bits.pop(), Some(i % 3 == 0 || i == 100));
+        }
+        assert_eq!(bits.pop(), None);
+        assert_eq!(bits, BitVector::default());
+    }
+
+    #[test]
+    fn test_capacity_follows_vector_rules() {
+        let bits = BitVector::from_elem(true, 1024);
+        assert_eq!(bits.capacity(), 1024);
+        let mut bits = BitVector::from_elem(true, 1025);
+        assert_eq!(bits.capacity(), 2048);
+        assert_eq!(bits.count_ones(), 1025);
+        for _ in 0..1000 {
+            bits.pop();
+        }
+        assert_eq!(bits.capacity(), 1024);
+    }
+
+    #[test]
+    fn test_insert_and_delete_match_vec() {
+        let mut bits = BitVector::new(0);
+        let mut expected = Vec::new();
+        let mut state = 99u32;
+        for _ in 0..5000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let bit = state >> 20 & 1 == 1;
+            if state >> 28 < 10 || expected.is_empty() {
+                let index = (state >> 8) as usize % (expected.len() + 1);
+                bits.insert(index, bit);
+                expected.insert(index, bit);
+            } else {
+                let index = (state >> 8) as usize % expected.len();
+                assert_eq!(bits.delete(index), expected.remove(index));
+            }
+            assert_eq!(bits.size(), expected.len());
+        }
+        assert!(bits.iter().eq(expected.iter().copied()));
+        assert_eq!(bits.count_ones(), expected.iter().filter(|&&bit| bit).count());
+    }
+
+    #[test]
+    fn test_rank_select_and_ones() {
+        let expected = random_bits(5, 1000);
+        let bits: BitVector = expected.iter().copied().collect();
+        let ones: Vec<usize> = (0..expected.len()).filter(|&i| expected[i]).collect();
+        assert!(bits.iter_ones().eq(ones.iter().copied()));
+        for index in 0..=expected.len() {
+            assert_eq!(bits.rank(index), expected[..index].iter().filter(|&&bit| bit).count());
+        }
+        for (n, &index) in ones.iter().enumerate() {
+            assert_eq!(bits.select(n), Some(index));
+            assert_eq!(bits.rank(index), n);
+        }
+        assert_eq!(bits.select(ones.len()), None);
+        assert_eq!(BitVector::new(0).iter_ones().next(), None);
+    }
+
+    #[test]
+    fn test_bitwise_operations() {
+        let a: BitVector = random_bits(1, 130).into_iter().collect();
+        let b: BitVector = random_bits(2, 130).into_iter().collect();
+        let check = |result: BitVector, op: fn(bool, bool) -> bool| {
+            assert!(result.iter().eq(a.iter().zip(b.iter()).map(|(x, y)| op(x, y))));
+        };
+        check(&a & &b, |x, y| x & y);
+        check(&a | &b, |x, y| x | y);
+        check(&a ^ &b, |x, y| x ^ y);
+
+        let inverted = !&a;
+        assert!(inverted.iter().eq(a.iter().map(|bit| !bit)));
+        assert_eq!(inverted.count_ones(), 130 - a.count_ones());
+        let mut c = a.clone();
+        c ^= &a;
+        assert_eq!(c.count_ones(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "different lengths")]
+    fn test_bitwise_operation_on_different_lengths_panics() {
+        let _ = &BitVector::from_elem(true, 3) & &BitVector::from_elem(true, 4);
+    }
+
+    #[test]
+    fn test_debug() {
+        let bits: BitVector = [true, false, true, true].into_iter().collect();
+        assert_eq!(format!("{:?}", bits), "BitVector[1011]");
+    }
+}
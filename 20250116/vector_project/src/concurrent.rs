@@ -0,0 +1,283 @@
+// Append-only vector that many threads can push to and read from at the same time without a lock:
+// * push(&self, item) - appends an item and returns the index it was given
+// * get(&self, index) - returns the item at an index once its push has completed
+// * size(), is_empty(), iter()
+//
+// Storage is split into segments of 16, 32, 64, ... slots, the same power-of-two sizes `Vector`
+// grows through. A segment is allocated the first time an index inside it is handed out and is
+// never moved or freed before the vector is dropped, so references returned by `get` stay valid
+// while other threads keep pushing. Each slot carries a flag that is set once its item is written,
+// which is what makes an item visible to readers.
+
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
+
+// log2 of the number of slots in the first segment
+const FIRST_SEGMENT_BITS: u32 = 4;
+
+// Number of slots in the first segment
+const FIRST_SEGMENT_LEN: usize = 1 << FIRST_SEGMENT_BITS;
+
+// Number of segments needed to address every index
+const SEGMENTS: usize = (usize::BITS - FIRST_SEGMENT_BITS) as usize;
+
+// A place for one item and the flag announcing that it has been written
+struct Slot<T> {
+    ready: AtomicBool,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+// Define a `ConcurrentVector` struct with its segment table and the number of indices handed out
+pub struct ConcurrentVector<T> {
+    segments: [AtomicPtr<Slot<T>>; SEGMENTS], // Segment k holds 16 << k slots, null until first used
+    reserved: AtomicUsize,                    // Number of indices handed out by `push`
+    _marker: PhantomData<T>,
+}
+
+// Items are moved in from the pushing thread and dropped by whichever thread drops the vector,
+// and shared references to them are handed to every reading thread
+unsafe impl<T: Send> Send for ConcurrentVector<T> {}
+unsafe impl<T: Send + Sync> Sync for ConcurrentVector<T> {}
+
+impl<T> ConcurrentVector<T> {
+    // Creates an empty vector; no memory is allocated until the first push
+    pub fn new() -> Self {
+        ConcurrentVector {
+            segments: [const { AtomicPtr::new(ptr::null_mut()) }; SEGMENTS],
+            reserved: AtomicUsize::new(0),
+            _marker: PhantomData,
+        }
+    }
+
+    // Appends an item and returns its index. Indices are handed out in the order pushes start,
+    // and existing items never move.
+    pub fn push(&self, item: T) -> usize {
+        let index = self.reserved.fetch_add(1, Ordering::Relaxed);
+        if index > usize::MAX - FIRST_SEGMENT_LEN {
+            panic!("Capacity overflow");
+        }
+        let (segment, offset) = locate(index);
+        let slots = self.segment(segment);
+        unsafe {
+            let slot = &*slots.add(offset);
+            (*slot.value.get()).write(item);
+            slot.ready.store(true, Ordering::Release);
+        }
+        index
+    }
+
+    // Returns the item at an index, or `None` if no push has completed for it yet
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.reserved.load(Ordering::Acquire) {
+            return None;
+        }
+        let (segment, offset) = locate(index);
+        let slots = self.segments[segment].load(Ordering::Acquire);
+        if slots.is_null() {
+            return None;
+        }
+        unsafe {
+            let slot = &*slots.add(offset);
+            if !slot.ready.load(Ordering::Acquire) {
+                return None;
+            }
+            Some((*slot.value.get()).assume_init_ref())
+        }
+    }
+
+    // Returns the number of indices handed out; pushes for the last few may still be in progress
+    pub fn size(&self) -> usize {
+        self.reserved.load(Ordering::Acquire)
+    }
+
+    // Checks if no push has started yet
+    pub fn is_empty(&self) -> bool {
+        self.size() == 0
+    }
+
+    // Returns an iterator over the items whose pushes have completed, in index order, skipping
+    // indices whose pushes are still in progress
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        (0..self.size()).filter_map(move |index| self.get(index))
+    }
+
+    // Returns segment `segment`, allocating it if no thread has yet
+    fn segment(&self, segment: usize) -> *mut Slot<T> {
+        let current = self.segments[segment].load(Ordering::Acquire);
+        if !current.is_null() {
+            return current;
+        }
+
+        // Zeroed memory marks every slot as not ready; if another thread installs its segment
+        // first, ours is freed and theirs used
+        let layout = segment_layout::<T>(segment);
+        let fresh = unsafe { alloc_zeroed(layout) as *mut Slot<T> };
+        if fresh.is_null() {
+            handle_alloc_error(layout);
+        }
+        match self.segments[segment].compare_exchange(ptr::null_mut(), fresh, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => fresh,
+            Err(installed) => {
+                unsafe { dealloc(fresh as *mut u8, layout) };
+                installed
+            }
+        }
+    }
+}
+
+impl<T> Default for ConcurrentVector<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Drop the items that were written, then free the segments
+impl<T> Drop for ConcurrentVector<T> {
+    fn drop(&mut self) {
+        for (segment, slots) in self.segments.iter_mut().enumerate() {
+            let slots = *slots.get_mut();
+            if slots.is_null() {
+                continue;
+            }
+            unsafe {
+                for offset in 0..FIRST_SEGMENT_LEN << segment {
+                    let slot = &mut *slots.add(offset);
+                    if *slot.ready.get_mut() {
+                        slot.value.get_mut().assume_init_drop();
+                    }
+                }
+                dealloc(slots as *mut u8, segment_layout::<T>(segment));
+            }
+        }
+    }
+}
+
+// Maps an index to its segment and the offset inside it
+fn locate(index: usize) -> (usize, usize) {
+    let biased = index + FIRST_SEGMENT_LEN;
+    let segment = (usize::BITS - 1 - biased.leading_zeros() - FIRST_SEGMENT_BITS) as usize;
+    (segment, biased - (FIRST_SEGMENT_LEN << segment))
+}
+
+// Layout of segment `segment`; only segments whose slots are addressable are ever allocated
+fn segment_layout<T>(segment: usize) -> Layout {
+    Layout::array::<Slot<T>>(FIRST_SEGMENT_LEN << segment).unwrap_or_else(|_| panic!("Capacity overflow"))
+}
+
+// Unit tests for the `ConcurrentVector` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn test_locate() {
+        assert_eq!(locate(0), (0, 0));
+        assert_eq!(locate(15), (0, 15));
+        assert_eq!(locate(16), (1, 0));
+        assert_eq!(locate(47), (1, 31));
+        assert_eq!(locate(48), (2, 0));
+        assert_eq!(locate(usize::MAX - FIRST_SEGMENT_LEN), (SEGMENTS - 1, (FIRST_SEGMENT_LEN << (SEGMENTS - 1)) - 1));
+    }
+
+    #[test]
+    fn test_single_thread() {
+        let vec = ConcurrentVector::new();
+        assert!(vec.is_empty());
+        for i in 0..100 {
+            assert_eq!(vec.push(i * 2), i);
+        }
+        assert_eq!(vec.size(), 100);
+        assert_eq!(vec.get(99), Some(&198));
+        assert_eq!(vec.get(100), None);
+        assert!(vec.iter().copied().eq((0..100).map(|i| i * 2)));
+    }
+
+    #[test]
+    fn test_references_stay_valid_while_growing() {
+        let vec = ConcurrentVector::new();
+        vec.push(String::from("first"));
+        let first = vec.get(0).unwrap();
+        for i in 0..10_000 {
+            vec.push(i.to_string());
+        }
+        assert_eq!(first, "first");
+    }
+
+    #[test]
+    fn test_concurrent_pushes_get_unique_indices() {
+        const THREADS: usize = 8;
+        const PER_THREAD: usize = 10_000;
+
+        let vec = ConcurrentVector::new();
+        let indices: Vec<Vec<usize>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..THREADS)
+                .map(|t| {
+                    let vec = &vec;
+                    scope.spawn(move || (0..PER_THREAD).map(|i| vec.push((t, i))).collect())
+                })
+                .collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+
+        // Every push got its own index, and the item at that index is the one pushed
+        assert_eq!(vec.size(), THREADS * PER_THREAD);
+        let mut seen = vec![false; THREADS * PER_THREAD];
+        for (t, thread_indices) in indices.iter().enumerate() {
+            for (i, &index) in thread_indices.iter().enumerate() {
+                assert!(!seen[index]);
+                seen[index] = true;
+                assert_eq!(vec.get(index), Some(&(t, i)));
+            }
+        }
+        assert_eq!(vec.iter().count(), THREADS * PER_THREAD);
+    }
+
+    #[test]
+    fn test_readers_run_alongside_writers() {
+        let vec = Arc::new(ConcurrentVector::new());
+        let writer = {
+            let vec = Arc::clone(&vec);
+            thread::spawn(move || {
+                for i in 0..50_000u64 {
+                    vec.push(i);
+                }
+            })
+        };
+
+        // A reader only ever sees fully written items, at the index they were pushed to
+        while !writer.is_finished() {
+            let size = vec.size();
+            for index in (0..size).step_by(997) {
+                if let Some(&value) = vec.get(index) {
+                    assert_eq!(value, index as u64);
+                }
+            }
+        }
+        writer.join().unwrap();
+        assert!(vec.iter().copied().eq(0..50_000));
+    }
+
+    #[test]
+    fn test_drops_every_item_once() {
+        let tracker = Arc::new(());
+        let vec = ConcurrentVector::new();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..1000 {
+                        vec.push(Arc::clone(&tracker));
+                    }
+                });
+            }
+        });
+        assert_eq!(Arc::strong_count(&tracker), 4001);
+        drop(vec);
+        assert_eq!(Arc::strong_count(&tracker), 1);
+    }
+}
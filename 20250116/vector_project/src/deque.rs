@@ -0,0 +1,615 @@
+// Double-ended queue on a growable circular buffer, for FIFO use where `Vector::prepend` is O(n):
+// * push_front(item), push_back(item) - add at either end in amortized O(1)
+// * pop_front(), pop_back() - remove from either end in O(1), shrinking like `Vector::pop`
+// * front(), back(), front_mut(), back_mut() - peek at either end
+// * at(index), get(index), get_mut(index), deque[index] - index 0 is the front
+// * iter(), iter_mut(), into_iter() - front to back, from either end
+// * as_slices() - the elements as two slices, front part first
+// * make_contiguous() - moves the elements into one slice and returns it
+// * size(), capacity(), is_empty(), clear()
+//
+// The buffer is allocated like a `Vector` buffer: the capacity starts at 16 or the next power of two,
+// doubles when full and halves once only a quarter is used. The front element sits at `head` and
+// the rest follow it, wrapping around to the start of the buffer; because the capacity is a power
+// of two, wrapping is a bit mask.
+
+use crate::growth::{Doubling, GrowthPolicy};
+use crate::vector::{capacity_for, deallocate, try_allocate, VectorError};
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::{Index, IndexMut};
+use std::ptr;
+use std::slice;
+
+// Define a `Deque` struct with a raw pointer to a circular buffer, the position of the front
+// element, the number of elements and the capacity
+pub struct Deque<T> {
+    data: *mut T,    // Raw pointer to a dynamically allocated circular array of T
+    head: usize,     // Index in the buffer of the front element
+    size: usize,     // Current number of elements in the deque
+    capacity: usize, // Number of slots in the buffer, always a power of two
+}
+
+// Like `Vector`, a `Deque` owns its elements and buffer exclusively
+unsafe impl<T: Send> Send for Deque<T> {}
+unsafe impl<T: Sync> Sync for Deque<T> {}
+
+impl<T> Deque<T> {
+    // Creates a new `Deque` with an initial capacity, defaulting to 16 if 0 is provided
+    pub fn new(initial_capacity: usize) -> Self {
+        Self::try_new(initial_capacity).unwrap_or_else(|err| err.raise())
+    }
+
+    // Creates a new `Deque` like `new`, but returns an error if the memory cannot be allocated
+    pub fn try_new(initial_capacity: usize) -> Result<Self, VectorError> {
+        let capacity = capacity_for(initial_capacity)?;
+        let data = try_allocate(capacity)?;
+        Ok(Deque { data, head: 0, size: 0, capacity })
+    }
+
+    // Returns the current number of elements in the deque
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Returns the number of elements the deque can hold without resizing
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Checks if the deque is empty
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    // Returns a clone of the element at a given index from the front, blows up if index out of bounds
+    pub fn at(&self, index: usize) -> T
+    where
+        T: Clone,
+    {
+        match self.get(index) {
+            Some(item) => item.clone(),
+            None => VectorError::IndexOutOfBounds { index, len: self.size }.raise(),
+        }
+    }
+
+    // Returns a reference to the element at a given index from the front, or `None` if out of bounds
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.size {
+            return None;
+        }
+        Some(unsafe { &*self.slot(index) })
+    }
+
+    // Returns a mutable reference to the element at a given index from the front, or `None` if out of bounds
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index >= self.size {
+            return None;
+        }
+        Some(unsafe { &mut *self.slot(index) })
+    }
+
+    // Returns the front element, or `None` if empty
+    pub fn front(&self) -> Option<&T> {
+        self.get(0)
+    }
+
+    // Returns the back element, or `None` if empty
+    pub fn back(&self) -> Option<&T> {
+        self.get(self.size.checked_sub(1)?)
+    }
+
+    // Returns the front element mutably, or `None` if empty
+    pub fn front_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(0)
+    }
+
+    // Returns the back element mutably, or `None` if empty
+    pub fn back_mut(&mut self) -> Option<&mut T> {
+        self.get_mut(self.size.checked_sub(1)?)
+    }
+
+    // Adds an element at the back, panics if the buffer cannot grow
+    pub fn push_back(&mut self, item: T) {
+        self.grow_for_one();
+        unsafe { self.slot(self.size).write(item) };
+        self.size += 1;
+    }
+
+    // Adds an element at the front, panics if the buffer cannot grow
+    pub fn push_front(&mut self, item: T) {
+        self.grow_for_one();
+        self.head = self.wrap(self.head + self.capacity - 1);
+        unsafe { self.data.add(self.head).write(item) };
+        self.size += 1;
+    }
+
+    // Removes the front element and returns it, `None` if empty
+    pub fn pop_front(&mut self) -> Option<T> {
+        let item = self.take_front()?;
+        self.shrink();
+        Some(item)
+    }
+
+    // Removes the back element and returns it, `None` if empty
+    pub fn pop_back(&mut self) -> Option<T> {
+        let item = self.take_back()?;
+        self.shrink();
+        Some(item)
+    }
+
+    // Returns the elements as two slices; the first holds the front of the deque and the second,
+    // empty unless the elements wrap around the end of the buffer, holds the rest
+    pub fn as_slices(&self) -> (&[T], &[T]) {
+        let (front, back) = self.ranges();
+        unsafe {
+            (
+                slice::from_raw_parts(self.data.add(front.0), front.1),
+                slice::from_raw_parts(self.data, back),
+            )
+        }
+    }
+
+    // Returns the elements as two mutable slices, like `as_slices`
+    pub fn as_mut_slices(&mut self) -> (&mut [T], &mut [T]) {
+        let (front, back) = self.ranges();
+        unsafe {
+            (
+                slice::from_raw_parts_mut(self.data.add(front.0), front.1),
+                slice::from_raw_parts_mut(self.data, back),
+            )
+        }
+    }
+
+    // Moves the elements so they no longer wrap around the end of the buffer and returns them as
+    // one slice. Elements that wrap are copied into a fresh buffer of the same capacity, once.
+    pub fn make_contiguous(&mut self) -> &mut [T] {
+        if self.head + self.size > self.capacity {
+            self.resize(self.capacity).unwrap_or_else(|err| err.raise());
+        }
+        unsafe { slice::from_raw_parts_mut(self.data.add(self.head), self.size) }
+    }
+
+    // Returns an iterator over references to the elements, front to back
+    pub fn iter(&self) -> Iter<'_, T> {
+        let (front, back) = self.as_slices();
+        Iter { front: front.iter(), back: back.iter() }
+    }
+
+    // Returns an iterator over mutable references to the elements, front to back
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        let (front, back) = self.as_mut_slices();
+        IterMut { front: front.iter_mut(), back: back.iter_mut() }
+    }
+
+    // Drops all elements and shrinks the buffer like `pop_front` would
+    pub fn clear(&mut self) {
+        let (front, back) = self.as_mut_slices();
+        let (front, back) = (front as *mut [T], back as *mut [T]);
+        // Nothing counts as live while destructors run, so a panicking one leaks instead of double dropping
+        self.size = 0;
+        self.head = 0;
+        unsafe {
+            ptr::drop_in_place(front);
+            ptr::drop_in_place(back);
+        }
+        self.shrink();
+    }
+
+    // Returns a pointer to the slot holding the element at `index` from the front
+    fn slot(&self, index: usize) -> *mut T {
+        unsafe { self.data.add(self.wrap(self.head + index)) }
+    }
+
+    // Maps a position past the end of the buffer back to its start
+    fn wrap(&self, position: usize) -> usize {
+        position & (self.capacity - 1)
+    }
+
+    // Returns the start and length of the front part and the length of the wrapped part
+    fn ranges(&self) -> ((usize, usize), usize) {
+        let front_len = self.size.min(self.capacity - self.head);
+        ((self.head, front_len), self.size - front_len)
+    }
+
+    // Removes the front element without shrinking
+    fn take_front(&mut self) -> Option<T> {
+        if self.size == 0 {
+            return None;
+        }
+        let item = unsafe { self.data.add(self.head).read() };
+        self.head = self.wrap(self.head + 1);
+        self.size -= 1;
+        Some(item)
+    }
+
+    // Removes the back element without shrinking
+    fn take_back(&mut self) -> Option<T> {
+        if self.size == 0 {
+            return None;
+        }
+        self.size -= 1;
+        Some(unsafe { self.slot(self.size).read() })
+    }
+
+    // Doubles the buffer if it is full
+    fn grow_for_one(&mut self) {
+        if self.size < self.capacity {
+            return;
+        }
+        let new_capacity = Doubling.grow(self.capacity, self.size + 1).ok_or(VectorError::CapacityOverflow);
+        if let Err(err) = new_capacity.and_then(|capacity| self.resize(capacity)) {
+            err.raise();
+        }
+    }
+
+    // Halves the buffer until at least a quarter of it is used, as `Vector` does by default
+    fn shrink(&mut self) {
+        let mut new_capacity = self.capacity;
+        while let Some(capacity) = Doubling.shrink(self.size, new_capacity) {
+            new_capacity = capacity;
+        }
+        if new_capacity < self.capacity {
+            let _ = self.resize(new_capacity);
+        }
+    }
+
+    // Moves the elements into a new buffer of `new_capacity` slots, front element first, leaving
+    // the deque untouched on failure
+    fn resize(&mut self, new_capacity: usize) -> Result<(), VectorError> {
+        let new_data = try_allocate(new_capacity)?;
+        let ((start, front_len), back_len) = self.ranges();
+        unsafe {
+            ptr::copy_nonoverlapping(self.data.add(start), new_data, front_len);
+            ptr::copy_nonoverlapping(self.data, new_data.add(front_len), back_len);
+            deallocate(self.data, self.capacity);
+        }
+        self.data = new_data;
+        self.head = 0;
+        self.capacity = new_capacity;
+        Ok(())
+    }
+}
+
+// Drop the remaining elements and then deallocate memory when the `Deque` is dropped
+impl<T> Drop for Deque<T> {
+    fn drop(&mut self) {
+        let (front, back) = self.as_mut_slices();
+        unsafe {
+            ptr::drop_in_place(front);
+            ptr::drop_in_place(back);
+            deallocate(self.data, self.capacity);
+        }
+    }
+}
+
+impl<T> Index<usize> for Deque<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match self.get(index) {
+            Some(item) => item,
+            None => VectorError::IndexOutOfBounds { index, len: self.size }.raise(),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for Deque<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        let len = self.size;
+        match self.get_mut(index) {
+            Some(item) => item,
+            None => VectorError::IndexOutOfBounds { index, len }.raise(),
+        }
+    }
+}
+
+impl<T: Clone> Clone for Deque<T> {
+    fn clone(&self) -> Self {
+        let mut deque = Deque::new(self.size);
+        deque.extend(self.iter().cloned());
+        deque
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Deque<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T> Default for Deque<T> {
+    fn default() -> Self {
+        Deque::new(0)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Deque<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size && self.iter().eq(other.iter())
+    }
+}
+
+impl<T: Eq> Eq for Deque<T> {}
+
+impl<T> FromIterator<T> for Deque<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut deque = Deque::new(iter.size_hint().0);
+        deque.extend(iter);
+        deque
+    }
+}
+
+// Appends every item at the back
+impl<T> Extend<T> for Deque<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push_back(item);
+        }
+    }
+}
+
+// Define an iterator over references to the elements of a `Deque`, front part first
+pub struct Iter<'a, T> {
+    front: slice::Iter<'a, T>,
+    back: slice::Iter<'a, T>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<&'a T> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+// Define an iterator over mutable references to the elements of a `Deque`, front part first
+pub struct IterMut<'a, T> {
+    front: slice::IterMut<'a, T>,
+    back: slice::IterMut<'a, T>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        self.front.next().or_else(|| self.back.next())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.front.len() + self.back.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+    fn next_back(&mut self) -> Option<&'a mut T> {
+        self.back.next_back().or_else(|| self.front.next_back())
+    }
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+// Define an owning iterator over the elements of a `Deque`; it never reallocates, and the
+// elements it has not yielded are dropped with it
+pub struct IntoIter<T> {
+    deque: Deque<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.deque.take_front()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.deque.size, Some(self.deque.size))
+    }
+}
+
+impl<T> DoubleEndedIterator for IntoIter<T> {
+    fn next_back(&mut self) -> Option<T> {
+        self.deque.take_back()
+    }
+}
+
+impl<T> ExactSizeIterator for IntoIter<T> {}
+
+impl<T> FusedIterator for IntoIter<T> {}
+
+impl<T> IntoIterator for Deque<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { deque: self }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Deque<T> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Deque<T> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+// Unit tests for the `Deque` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_fifo_and_lifo() {
+        let mut deque = Deque::new(0);
+        assert!(deque.is_empty());
+        assert_eq!(deque.capacity(), 16);
+        for i in 0..5 {
+            deque.push_back(i);
+        }
+        deque.push_front(-1);
+        assert_eq!((deque.front(), deque.back()), (Some(&-1), Some(&4)));
+        assert_eq!(deque.at(3), 2);
+        assert_eq!(deque[0], -1);
+        assert_eq!(deque.get(6), None);
+        assert_eq!(deque.pop_front(), Some(-1));
+        assert_eq!(deque.pop_back(), Some(4));
+        assert_eq!(deque.pop_front(), Some(0));
+        *deque.front_mut().unwrap() = 10;
+        *deque.back_mut().unwrap() += 20;
+        assert!(deque.iter().copied().eq([10, 2, 23]));
+        assert!(deque.iter().rev().copied().eq([23, 2, 10]));
+    }
+
+    #[test]
+    fn test_wraps_and_grows_in_order() {
+        // Keep the head moving so the elements wrap around the end of the buffer, then grow
+        let mut deque = Deque::new(0);
+        for i in 0..12 {
+            deque.push_back(i);
+        }
+        for _ in 0..10 {
+            deque.pop_front();
+        }
+        for i in 12..20 {
+            deque.push_back(i);
+        }
+        let (front, back) = deque.as_slices();
+        assert_eq!((front.len(), back.len()), (6, 4));
+        for i in 20..40 {
+            deque.push_back(i);
+        }
+        assert_eq!(deque.capacity(), 32);
+        assert!(deque.iter().copied().eq(10..40));
+
+        // Pushing to the front wraps the head backwards
+        let mut deque = Deque::new(0);
+        for i in 0..100 {
+            deque.push_front(i);
+        }
+        assert!(deque.iter().copied().eq((0..100).rev()));
+        assert_eq!(deque.capacity(), 128);
+    }
+
+    #[test]
+    fn test_shrinks_like_vector() {
+        let mut deque: Deque<i32> = (0..64).collect();
+        assert_eq!(deque.capacity(), 64);
+        while deque.size() > 16 {
+            deque.pop_front();
+        }
+        assert_eq!(deque.capacity(), 32);
+        while deque.pop_back().is_some() {}
+        assert_eq!(deque.capacity(), 16);
+    }
+
+    #[test]
+    fn test_make_contiguous() {
+        let mut deque = Deque::new(0);
+        for i in 0..10 {
+            deque.push_back(i);
+            deque.push_front(-i - 1);
+        }
+        assert!(!deque.as_slices().1.is_empty());
+        let expected: Vec<i32> = (-10..10).collect();
+        assert_eq!(deque.make_contiguous(), &expected[..]);
+        assert_eq!(deque.as_slices(), (&expected[..], &[][..]));
+
+        // Already contiguous elements stay where they are
+        let ptr = deque.make_contiguous().as_ptr();
+        assert_eq!(deque.make_contiguous().as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_matches_vecdeque() {
+        let mut deque = Deque::new(0);
+        let mut expected = VecDeque::new();
+        let mut state = 7u32;
+        for step in 0..20_000 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            match (state >> 16) % 5 {
+                0 | 1 => {
+                    deque.push_back(step);
+                    expected.push_back(step);
+                }
+                2 => {
+                    deque.push_front(step);
+                    expected.push_front(step);
+                }
+                3 => assert_eq!(deque.pop_front(), expected.pop_front()),
+                _ => assert_eq!(deque.pop_back(), expected.pop_back()),
+            }
+            assert_eq!(deque.size(), expected.len());
+            assert_eq!(deque.front(), expected.front());
+            assert_eq!(deque.back(), expected.back());
+        }
+        assert!(deque.iter().eq(expected.iter()));
+        assert!(deque.into_iter().rev().eq(expected.into_iter().rev()));
+    }
+
+    #[test]
+    fn test_drops_every_element_once() {
+        let tracker = Rc::new(());
+        let mut deque = Deque::new(0);
+        for _ in 0..20 {
+            deque.push_back(Rc::clone(&tracker));
+            deque.push_front(Rc::clone(&tracker));
+        }
+        drop(deque.pop_front());
+        assert_eq!(Rc::strong_count(&tracker), 40);
+
+        // A partly consumed owning iterator drops the rest
+        let mut iter = deque.clone().into_iter();
+        iter.next();
+        iter.next_back();
+        assert_eq!(Rc::strong_count(&tracker), 77);
+        drop(iter);
+        assert_eq!(Rc::strong_count(&tracker), 40);
+
+        deque.clear();
+        assert_eq!(Rc::strong_count(&tracker), 1);
+        assert_eq!(deque.capacity(), 16);
+    }
+
+    #[test]
+    fn test_zero_sized_elements() {
+        let mut deque = Deque::new(0);
+        for _ in 0..100 {
+            deque.push_front(());
+        }
+        assert_eq!(deque.size(), 100);
+        assert_eq!(deque.iter_mut().count(), 100);
+        assert_eq!(deque.pop_back(), Some(()));
+    }
+}
@@ -0,0 +1,144 @@
+// Read-only vector for sharing between threads, created by `Vector::freeze`:
+// * clone() - shares the same elements, only bumping a reference count
+// * Deref to a slice - every read-only slice method, `len()`, indexing and iteration
+// * thaw() - turns the frozen vector back into a `Vector`, copying only if it is still shared
+//
+// `FrozenVector` is `Send` and `Sync` whenever the element type is `Send` and `Sync`.
+
+use crate::allocator::{Allocator, Global};
+use crate::growth::{Doubling, GrowthPolicy};
+use crate::vector::Vector;
+use std::fmt;
+use std::ops::Deref;
+use std::sync::Arc;
+
+// Define a `FrozenVector` struct sharing one `Vector` between all of its clones
+pub struct FrozenVector<T, P = Doubling, A: Allocator = Global> {
+    inner: Arc<Vector<T, P, A>>, // The frozen vector, never mutated again
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> FrozenVector<T, P, A> {
+    // Returns the elements as a slice
+    pub fn as_slice(&self) -> &[T] {
+        self.inner.as_slice()
+    }
+
+    // Returns the number of elements
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    // Turns the frozen vector back into a mutable `Vector`; the elements are moved if this is the
+    // last clone and copied otherwise
+    pub fn thaw(self) -> Vector<T, P, A>
+    where
+        T: Clone,
+        P: Clone,
+        A: Clone,
+    {
+        Arc::try_unwrap(self.inner).unwrap_or_else(|shared| (*shared).clone())
+    }
+}
+
+impl<T, P, A: Allocator> From<Vector<T, P, A>> for FrozenVector<T, P, A> {
+    fn from(vec: Vector<T, P, A>) -> Self {
+        FrozenVector { inner: Arc::new(vec) }
+    }
+}
+
+// Cloning shares the elements instead of copying them
+impl<T, P, A: Allocator> Clone for FrozenVector<T, P, A> {
+    fn clone(&self) -> Self {
+        FrozenVector { inner: Arc::clone(&self.inner) }
+    }
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> Deref for FrozenVector<T, P, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T: fmt::Debug, P: GrowthPolicy, A: Allocator> fmt::Debug for FrozenVector<T, P, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: PartialEq, P: GrowthPolicy, A: Allocator> PartialEq for FrozenVector<T, P, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, P: GrowthPolicy, A: Allocator> Eq for FrozenVector<T, P, A> {}
+
+impl<'a, T, P: GrowthPolicy, A: Allocator> IntoIterator for &'a FrozenVector<T, P, A> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+// Unit tests for the `FrozenVector` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocator::CountingAllocator;
+    use crate::vector;
+    use std::thread;
+
+    #[test]
+    fn test_freeze_and_thaw() {
+        let frozen = vector![1, 2, 3].freeze();
+        assert_eq!(frozen.size(), 3);
+        assert_eq!(frozen[1], 2);
+        assert_eq!(frozen.iter().sum::<i32>(), 6);
+
+        // Clones share the same buffer
+        let copy = frozen.clone();
+        assert_eq!(copy.as_ptr(), frozen.as_ptr());
+        assert_eq!(copy, frozen);
+
+        // Thawing a shared vector copies it, thawing the last clone does not
+        let mut thawed = copy.thaw();
+        thawed.push(4);
+        assert_eq!(thawed, [1, 2, 3, 4]);
+        let ptr = frozen.as_ptr();
+        let thawed = frozen.thaw();
+        assert_eq!(thawed.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_share_across_threads() {
+        let frozen: FrozenVector<String> = (0..1000).map(|i| i.to_string()).collect::<Vector<_>>().freeze();
+        let handles: Vec<_> = (0..4)
+            .map(|t| {
+                let frozen = frozen.clone();
+                thread::spawn(move || frozen.iter().skip(t).step_by(4).filter(|s| s.ends_with('7')).count())
+            })
+            .collect();
+        let count: usize = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+        assert_eq!(count, 100);
+    }
+
+    #[test]
+    fn test_freeze_vector_from_another_allocator() {
+        let alloc = CountingAllocator::new();
+        let mut vec = Vector::new_in(0, alloc.clone());
+        vec.extend_from_slice(&[1, 2, 3]);
+        let frozen = vec.freeze();
+        assert_eq!(frozen, frozen.clone());
+
+        // A thawed copy takes its buffer from the same allocator
+        let thawed = frozen.clone().thaw();
+        assert_eq!(thawed, [1, 2, 3]);
+        assert_eq!(alloc.allocations(), 2);
+        drop((frozen, thawed));
+        assert_eq!(alloc.live_bytes(), 0);
+    }
+}
@@ -0,0 +1,220 @@
+// Growth and shrink policies for `Vector`:
+// * GrowthPolicy - decides the new capacity when the buffer is full or mostly empty
+// * Doubling - double on growth, halve when only a quarter is used (the default)
+// * OneAndHalf - grow by 1.5x, which wastes less memory at the cost of more reallocations
+// * FixedIncrement - grow and shrink by a fixed number of elements
+// * NeverShrink - wraps another policy and keeps the buffer at its peak capacity
+// * Hysteresis - wraps another policy and shrinks to a target load once usage drops below a low-water mark
+//
+// Capacities never shrink below the minimum capacity of 16.
+
+// Smallest capacity a policy will shrink a buffer to
+pub const MIN_CAPACITY: usize = 16;
+
+// Decides how the capacity of a buffer changes as elements are added and removed
+pub trait GrowthPolicy {
+    // Returns the capacity to grow a buffer of `capacity` to so that `required` elements fit,
+    // or `None` if that capacity would overflow
+    fn grow(&self, capacity: usize, required: usize) -> Option<usize>;
+
+    // Returns the capacity to shrink a buffer of `capacity` holding `size` elements to,
+    // or `None` to keep the current buffer.
+    // By default the buffer is halved once only a quarter of it is used.
+    fn shrink(&self, size: usize, capacity: usize) -> Option<usize> {
+        if size <= capacity / 4 && capacity > MIN_CAPACITY {
+            Some((capacity / 2).max(MIN_CAPACITY))
+        } else {
+            None
+        }
+    }
+}
+
+// Doubles the capacity until the required number of elements fit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Doubling;
+
+impl GrowthPolicy for Doubling {
+    fn grow(&self, capacity: usize, required: usize) -> Option<usize> {
+        let mut new_capacity = capacity.max(1);
+        while new_capacity < required {
+            new_capacity = new_capacity.checked_mul(2)?;
+        }
+        Some(new_capacity)
+    }
+}
+
+// Grows the capacity by half of itself until the required number of elements fit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OneAndHalf;
+
+impl GrowthPolicy for OneAndHalf {
+    fn grow(&self, capacity: usize, required: usize) -> Option<usize> {
+        let mut new_capacity = capacity.max(2);
+        while new_capacity < required {
+            new_capacity = new_capacity.checked_add(new_capacity / 2)?;
+        }
+        Some(new_capacity)
+    }
+}
+
+// Grows the capacity in steps of `step` elements and gives a step back once two steps are unused
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedIncrement {
+    step: usize, // Number of elements added or released at a time
+}
+
+impl FixedIncrement {
+    // Creates a policy that grows by `step` elements, panics if `step` is 0
+    pub fn new(step: usize) -> Self {
+        assert!(step > 0, "FixedIncrement step must be greater than 0");
+        FixedIncrement { step }
+    }
+
+    // Returns the number of elements added or released at a time
+    pub fn step(&self) -> usize {
+        self.step
+    }
+}
+
+impl GrowthPolicy for FixedIncrement {
+    fn grow(&self, capacity: usize, required: usize) -> Option<usize> {
+        if required <= capacity {
+            return Some(capacity);
+        }
+        let steps = (required - capacity).div_ceil(self.step);
+        capacity.checked_add(steps.checked_mul(self.step)?)
+    }
+
+    fn shrink(&self, size: usize, capacity: usize) -> Option<usize> {
+        let unused = capacity - size;
+        // A step too large to double can never be unused twice; two unused steps also mean the
+        // capacity is larger than a step, so it cannot underflow
+        let two_steps = self.step.checked_mul(2)?;
+        if unused >= two_steps && capacity - self.step >= MIN_CAPACITY {
+            Some(capacity - self.step)
+        } else {
+            None
+        }
+    }
+}
+
+// Grows like the wrapped policy but never releases memory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NeverShrink<P = Doubling>(pub P);
+
+impl<P: GrowthPolicy> GrowthPolicy for NeverShrink<P> {
+    fn grow(&self, capacity: usize, required: usize) -> Option<usize> {
+        self.0.grow(capacity, required)
+    }
+
+    fn shrink(&self, _size: usize, _capacity: usize) -> Option<usize> {
+        None
+    }
+}
+
+// Grows like the wrapped policy; shrinks only once usage drops below `low_percent` of the capacity,
+// and then to a capacity that is `target_percent` full. The gap between the two thresholds keeps
+// alternating pushes and pops from reallocating on every call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Hysteresis<P = Doubling> {
+    growth: P,             // Policy used when the buffer is full
+    low_percent: usize,    // Usage, in percent of the capacity, below which the buffer shrinks
+    target_percent: usize, // Usage, in percent of the new capacity, right after shrinking
+}
+
+impl<P> Hysteresis<P> {
+    // Creates a hysteresis policy, panics unless 0 < low_percent < target_percent <= 100
+    pub fn new(growth: P, low_percent: usize, target_percent: usize) -> Self {
+        assert!(
+            0 < low_percent && low_percent < target_percent && target_percent <= 100,
+            "Hysteresis thresholds must satisfy 0 < low_percent < target_percent <= 100"
+        );
+        Hysteresis { growth, low_percent, target_percent }
+    }
+}
+
+impl Default for Hysteresis {
+    // Shrinks once less than 25% is used, leaving the buffer 50% full
+    fn default() -> Self {
+        Hysteresis::new(Doubling, 25, 50)
+    }
+}
+
+impl<P: GrowthPolicy> GrowthPolicy for Hysteresis<P> {
+    fn grow(&self, capacity: usize, required: usize) -> Option<usize> {
+        self.growth.grow(capacity, required)
+    }
+
+    fn shrink(&self, size: usize, capacity: usize) -> Option<usize> {
+        if capacity <= MIN_CAPACITY || size.saturating_mul(100) >= capacity.saturating_mul(self.low_percent) {
+            return None;
+        }
+        let target = size.saturating_mul(100).div_ceil(self.target_percent).max(MIN_CAPACITY);
+        if target < capacity {
+            Some(target)
+        } else {
+            None
+        }
+    }
+}
+
+// Unit tests for the growth policies
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_doubling() {
+        assert_eq!(Doubling.grow(16, 17), Some(32));
+        assert_eq!(Doubling.grow(16, 100), Some(128));
+        assert_eq!(Doubling.grow(usize::MAX / 2 + 1, usize::MAX), None);
+        assert_eq!(Doubling.shrink(8, 64), Some(32));
+        assert_eq!(Doubling.shrink(17, 64), None);
+        assert_eq!(Doubling.shrink(0, 16), None);
+    }
+
+    #[test]
+    fn test_one_and_half() {
+        assert_eq!(OneAndHalf.grow(16, 17), Some(24));
+        assert_eq!(OneAndHalf.grow(16, 40), Some(54));
+        assert_eq!(OneAndHalf.grow(usize::MAX - 1, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_fixed_increment() {
+        let policy = FixedIncrement::new(10);
+        assert_eq!(policy.grow(16, 17), Some(26));
+        assert_eq!(policy.grow(16, 45), Some(46));
+        assert_eq!(policy.shrink(26, 46), Some(36));
+        assert_eq!(policy.shrink(27, 46), None);
+        assert_eq!(policy.shrink(0, 20), None);
+
+        // Steps too large to double never shrink
+        let policy = FixedIncrement::new(usize::MAX / 2 + 1);
+        assert_eq!(policy.shrink(0, 64), None);
+        assert_eq!(policy.shrink(0, usize::MAX), None);
+    }
+
+    #[test]
+    fn test_never_shrink() {
+        let policy = NeverShrink(Doubling);
+        assert_eq!(policy.grow(16, 17), Some(32));
+        assert_eq!(policy.shrink(0, 1024), None);
+    }
+
+    #[test]
+    fn test_hysteresis() {
+        let policy = Hysteresis::default();
+        assert_eq!(policy.grow(16, 17), Some(32));
+        assert_eq!(policy.shrink(16, 64), None);
+        assert_eq!(policy.shrink(15, 64), Some(30));
+        assert_eq!(policy.shrink(1, 64), Some(16));
+        assert_eq!(policy.shrink(3, 16), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Hysteresis thresholds")]
+    fn test_hysteresis_rejects_inverted_thresholds() {
+        Hysteresis::new(Doubling, 60, 50);
+    }
+}
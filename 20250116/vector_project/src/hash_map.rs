@@ -0,0 +1,783 @@
+// Hash map with open addressing, storing its slots in a `Vector`:
+// * insert(key, value) - adds or replaces an entry, returning the old value
+// * get(key), get_mut(key), contains_key(key), map[key] - look up an entry
+// * remove(key) - removes an entry and returns its value
+// * entry(key) - looks up a key once to read, insert or modify its entry
+// * iter(), iter_mut(), keys(), values(), into_iter() - visit the entries in slot order
+// * with_hasher(hasher) - plug in any `BuildHasher`, `RandomState` by default
+// * with_hasher_in(hasher, alloc) - keep the slots in another allocator (see `allocator`)
+//
+// Collisions are resolved by linear probing: a key lives in the first slot at or after
+// `hash & (slots - 1)` that is free. Removing an entry leaves a tombstone so probes for other keys
+// keep going past it; tombstones are reused by inserts and cleared whenever the table is rebuilt.
+// The number of slots follows `Vector`'s default capacity rule: it is a power of two, at least 16,
+// doubles once entries and tombstones fill 3/4 of the slots and halves once entries fill only a
+// quarter of that.
+
+use crate::allocator::{Allocator, Global};
+use crate::growth::{Doubling, MIN_CAPACITY};
+use crate::vector::{self, Vector, VectorError};
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::{BuildHasher, Hash};
+use std::iter::FusedIterator;
+use std::mem;
+use std::ops::Index;
+use std::slice;
+
+// State of one slot of the table
+#[derive(Clone)]
+enum Slot<K, V> {
+    Empty,                                // Never used since the table was last rebuilt; ends a probe
+    Deleted,                              // Held an entry that was removed; probes continue past it
+    Full { hash: u64, key: K, value: V }, // Holds an entry and its hash, kept for rebuilding
+}
+
+// Define a `HashMap` struct with its slots, the number of entries and tombstones, and the hasher
+pub struct HashMap<K, V, S = RandomState, A: Allocator = Global> {
+    slots: Vector<Slot<K, V>, Doubling, A>, // Always a power of two slots, at least 16
+    len: usize,                             // Number of full slots
+    tombstones: usize,                      // Number of deleted slots
+    hasher: S,                              // Builds the hasher for every key
+}
+
+impl<K: Hash + Eq, V> HashMap<K, V> {
+    // Creates an empty map with 16 slots and a randomly seeded hasher
+    pub fn new() -> Self {
+        Self::with_hasher(RandomState::new())
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher> HashMap<K, V, S> {
+    // Creates an empty map hashing keys with `hasher`
+    pub fn with_hasher(hasher: S) -> Self {
+        Self::with_hasher_in(hasher, Global)
+    }
+}
+
+// Rebuilding the table takes a fresh one from the same allocator, so the allocator is cloned
+impl<K: Hash + Eq, V, S: BuildHasher, A: Allocator + Clone> HashMap<K, V, S, A> {
+    // Creates an empty map hashing keys with `hasher` and taking its slots from `alloc`
+    pub fn with_hasher_in(hasher: S, alloc: A) -> Self {
+        HashMap { slots: empty_slots(MIN_CAPACITY, alloc), len: 0, tombstones: 0, hasher }
+    }
+
+    // Returns the number of entries
+    pub fn size(&self) -> usize {
+        self.len
+    }
+
+    // Checks if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // Returns the number of slots in the table
+    pub fn capacity(&self) -> usize {
+        self.slots.size()
+    }
+
+    // Returns the hasher builder
+    pub fn hasher(&self) -> &S {
+        &self.hasher
+    }
+
+    // Inserts an entry and returns the value it replaced, if the key was already present
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => Some(entry.insert(value)),
+            Entry::Vacant(entry) => {
+                entry.insert(value);
+                None
+            }
+        }
+    }
+
+    // Returns a reference to the value for a key, `None` if the key is not present
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match &self.slots[self.find(key)?] {
+            Slot::Full { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    // Returns a mutable reference to the value for a key, `None` if the key is not present
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        match &mut self.slots[index] {
+            Slot::Full { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    // Checks if a key is present
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.find(key).is_some()
+    }
+
+    // Removes the entry for a key and returns its value, `None` if the key is not present
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let index = self.find(key)?;
+        Some(self.remove_at(index).1)
+    }
+
+    // Looks up a key once, for reading, inserting or modifying its entry. Makes room for one more
+    // entry first, so inserting through a vacant entry never rebuilds the table.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V, S, A> {
+        self.grow_for_one();
+        let hash = self.hasher.hash_one(&key);
+        let mut reusable = None;
+        let mut index = self.home(hash);
+        loop {
+            match &self.slots[index] {
+                Slot::Empty => break,
+                Slot::Deleted => {
+                    reusable.get_or_insert(index);
+                }
+                Slot::Full { hash: found, key: existing, .. } => {
+                    if *found == hash && *existing == key {
+                        return Entry::Occupied(OccupiedEntry { map: self, index });
+                    }
+                }
+            }
+            index = self.next(index);
+        }
+        let index = reusable.unwrap_or(index);
+        Entry::Vacant(VacantEntry { map: self, index, hash, key })
+    }
+
+    // Removes all entries and goes back to a table of 16 slots
+    pub fn clear(&mut self) {
+        self.slots = empty_slots(MIN_CAPACITY, self.slots.allocator().clone());
+        self.len = 0;
+        self.tombstones = 0;
+    }
+
+    // Returns the index of the slot holding a key
+    fn find<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let hash = self.hasher.hash_one(key);
+        let mut index = self.home(hash);
+        loop {
+            match &self.slots[index] {
+                Slot::Empty => return None,
+                Slot::Full { hash: found, key: existing, .. } if *found == hash && existing.borrow() == key => {
+                    return Some(index);
+                }
+                _ => index = self.next(index),
+            }
+        }
+    }
+
+    // Removes the entry at a full slot, leaving a tombstone, and shrinks the table if it emptied out
+    fn remove_at(&mut self, index: usize) -> (K, V) {
+        let Slot::Full { key, value, .. } = mem::replace(&mut self.slots[index], Slot::Deleted) else {
+            unreachable!("remove_at called on a slot without an entry");
+        };
+        self.len -= 1;
+        self.tombstones += 1;
+        let slots = self.capacity();
+        if slots > MIN_CAPACITY && self.len * 16 <= slots * 3 {
+            self.rebuild(slots / 2);
+        }
+        (key, value)
+    }
+
+    // Makes sure one more entry fits below the maximum load. If tombstones make up most of the
+    // load, the table is rebuilt at the same size instead of doubling.
+    fn grow_for_one(&mut self) {
+        let slots = self.capacity();
+        if (self.len + self.tombstones + 1) * 4 <= slots * 3 {
+            return;
+        }
+        if (self.len + 1) * 2 <= slots {
+            self.rebuild(slots);
+        } else {
+            let doubled = slots.checked_mul(2).unwrap_or_else(|| VectorError::CapacityOverflow.raise());
+            self.rebuild(doubled);
+        }
+    }
+
+    // Moves every entry into a fresh table of `slots` slots, dropping the tombstones. Stored hashes
+    // place the entries without hashing or comparing keys again.
+    fn rebuild(&mut self, slots: usize) {
+        let table = empty_slots(slots, self.slots.allocator().clone());
+        let old = mem::replace(&mut self.slots, table);
+        self.tombstones = 0;
+        for slot in old {
+            if let Slot::Full { hash, key, value } = slot {
+                let mut index = self.home(hash);
+                while let Slot::Full { .. } = self.slots[index] {
+                    index = self.next(index);
+                }
+                self.slots[index] = Slot::Full { hash, key, value };
+            }
+        }
+    }
+
+    // Returns the slot where the probe for a hash starts
+    fn home(&self, hash: u64) -> usize {
+        hash as usize & (self.capacity() - 1)
+    }
+
+    // Returns the slot after `index`, wrapping around the end of the table
+    fn next(&self, index: usize) -> usize {
+        (index + 1) & (self.capacity() - 1)
+    }
+}
+
+impl<K, V, S, A: Allocator> HashMap<K, V, S, A> {
+    // Returns an iterator over the entries in slot order
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter { slots: self.slots.as_slice().iter(), remaining: self.len }
+    }
+
+    // Returns an iterator over the entries in slot order, with mutable references to the values
+    pub fn iter_mut(&mut self) -> IterMut<'_, K, V> {
+        IterMut { slots: self.slots.as_mut_slice().iter_mut(), remaining: self.len }
+    }
+
+    // Returns an iterator over the keys in slot order
+    pub fn keys(&self) -> impl ExactSizeIterator<Item = &K> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    // Returns an iterator over the values in slot order
+    pub fn values(&self) -> impl ExactSizeIterator<Item = &V> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+// Allocates a table of `slots` empty slots from `alloc`
+fn empty_slots<K, V, A: Allocator>(slots: usize, alloc: A) -> Vector<Slot<K, V>, Doubling, A> {
+    let mut table = Vector::with_capacity_in(slots, alloc);
+    for _ in 0..slots {
+        table.push(Slot::Empty);
+    }
+    table
+}
+
+// Define an entry of a `HashMap`, found by `entry(key)`, that is either present or vacant
+pub enum Entry<'a, K, V, S, A: Allocator = Global> {
+    Occupied(OccupiedEntry<'a, K, V, S, A>),
+    Vacant(VacantEntry<'a, K, V, S, A>),
+}
+
+// Define an entry whose key is present, pointing at its slot
+pub struct OccupiedEntry<'a, K, V, S, A: Allocator = Global> {
+    map: &'a mut HashMap<K, V, S, A>,
+    index: usize,
+}
+
+// Define an entry whose key is absent, holding the key and the slot it will go into
+pub struct VacantEntry<'a, K, V, S, A: Allocator = Global> {
+    map: &'a mut HashMap<K, V, S, A>,
+    index: usize,
+    hash: u64,
+    key: K,
+}
+
+impl<'a, K: Hash + Eq, V, S: BuildHasher, A: Allocator + Clone> Entry<'a, K, V, S, A> {
+    // Returns the value, inserting `default` first if the key is absent
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    // Returns the value, inserting the result of `default` first if the key is absent
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    // Returns the value, inserting `V::default()` first if the key is absent
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    // Calls `modify` on the value if the key is present
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, modify: F) -> Self {
+        if let Entry::Occupied(entry) = &mut self {
+            modify(entry.get_mut());
+        }
+        self
+    }
+
+    // Returns the key of the entry
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => &entry.key,
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq, V, S: BuildHasher, A: Allocator + Clone> OccupiedEntry<'a, K, V, S, A> {
+    // Returns the key
+    pub fn key(&self) -> &K {
+        self.parts().0
+    }
+
+    // Returns the value
+    pub fn get(&self) -> &V {
+        self.parts().1
+    }
+
+    // Returns the value mutably
+    pub fn get_mut(&mut self) -> &mut V {
+        match &mut self.map.slots[self.index] {
+            Slot::Full { value, .. } => value,
+            _ => unreachable!("occupied entry without an entry in its slot"),
+        }
+    }
+
+    // Returns the value mutably, for as long as the map was borrowed
+    pub fn into_mut(self) -> &'a mut V {
+        match &mut self.map.slots[self.index] {
+            Slot::Full { value, .. } => value,
+            _ => unreachable!("occupied entry without an entry in its slot"),
+        }
+    }
+
+    // Replaces the value and returns the old one
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    // Removes the entry and returns its value
+    pub fn remove(self) -> V {
+        self.map.remove_at(self.index).1
+    }
+
+    fn parts(&self) -> (&K, &V) {
+        match &self.map.slots[self.index] {
+            Slot::Full { key, value, .. } => (key, value),
+            _ => unreachable!("occupied entry without an entry in its slot"),
+        }
+    }
+}
+
+impl<'a, K: Hash + Eq, V, S: BuildHasher, A: Allocator + Clone> VacantEntry<'a, K, V, S, A> {
+    // Returns the key that would be inserted
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    // Inserts the value under the entry's key and returns it mutably
+    pub fn insert(self, value: V) -> &'a mut V {
+        let map = self.map;
+        if let Slot::Deleted = map.slots[self.index] {
+            map.tombstones -= 1;
+        }
+        map.len += 1;
+        map.slots[self.index] = Slot::Full { hash: self.hash, key: self.key, value };
+        match &mut map.slots[self.index] {
+            Slot::Full { value, .. } => value,
+            _ => unreachable!("vacant entry was just filled"),
+        }
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default, A: Allocator + Clone + Default> Default for HashMap<K, V, S, A> {
+    fn default() -> Self {
+        Self::with_hasher_in(S::default(), A::default())
+    }
+}
+
+impl<K: Clone, V: Clone, S: Clone, A: Allocator + Clone> Clone for HashMap<K, V, S, A> {
+    fn clone(&self) -> Self {
+        HashMap { slots: self.slots.clone(), len: self.len, tombstones: self.tombstones, hasher: self.hasher.clone() }
+    }
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, S, A: Allocator> fmt::Debug for HashMap<K, V, S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+// Maps are equal if they hold the same entries, wherever they sit in their tables
+impl<K: Hash + Eq, V: PartialEq, S: BuildHasher, A: Allocator + Clone> PartialEq for HashMap<K, V, S, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<K: Hash + Eq, V: Eq, S: BuildHasher, A: Allocator + Clone> Eq for HashMap<K, V, S, A> {}
+
+impl<K, Q, V, S, A> Index<&Q> for HashMap<K, V, S, A>
+where
+    K: Hash + Eq + Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+    S: BuildHasher,
+    A: Allocator + Clone,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("Key not found in HashMap")
+    }
+}
+
+impl<K: Hash + Eq, V, S: BuildHasher + Default, A: Allocator + Clone + Default> FromIterator<(K, V)>
+    for HashMap<K, V, S, A>
+{
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        map.extend(iter);
+        map
+    }
+}
+
+// Inserts every pair, later values replacing earlier ones for the same key
+impl<K: Hash + Eq, V, S: BuildHasher, A: Allocator + Clone> Extend<(K, V)> for HashMap<K, V, S, A> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+// Define an iterator over references to the entries of a `HashMap`
+pub struct Iter<'a, K, V> {
+    slots: slice::Iter<'a, Slot<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a V)> {
+        for slot in self.slots.by_ref() {
+            if let Slot::Full { key, value, .. } = slot {
+                self.remaining -= 1;
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for Iter<'_, K, V> {}
+
+impl<K, V> FusedIterator for Iter<'_, K, V> {}
+
+// Define an iterator over the entries of a `HashMap` with mutable references to the values
+pub struct IterMut<'a, K, V> {
+    slots: slice::IterMut<'a, Slot<K, V>>,
+    remaining: usize,
+}
+
+impl<'a, K, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
+        for slot in self.slots.by_ref() {
+            if let Slot::Full { key, value, .. } = slot {
+                self.remaining -= 1;
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V> ExactSizeIterator for IterMut<'_, K, V> {}
+
+impl<K, V> FusedIterator for IterMut<'_, K, V> {}
+
+// Define an owning iterator over the entries of a `HashMap`
+pub struct IntoIter<K, V, A: Allocator = Global> {
+    slots: vector::IntoIter<Slot<K, V>, A>,
+    remaining: usize,
+}
+
+impl<K, V, A: Allocator> Iterator for IntoIter<K, V, A> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<(K, V)> {
+        for slot in self.slots.by_ref() {
+            if let Slot::Full { key, value, .. } = slot {
+                self.remaining -= 1;
+                return Some((key, value));
+            }
+        }
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<K, V, A: Allocator> ExactSizeIterator for IntoIter<K, V, A> {}
+
+impl<K, V, A: Allocator> FusedIterator for IntoIter<K, V, A> {}
+
+impl<K, V, S, A: Allocator> IntoIterator for HashMap<K, V, S, A> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V, A>;
+
+    fn into_iter(self) -> IntoIter<K, V, A> {
+        IntoIter { slots: self.slots.into_iter(), remaining: self.len }
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> IntoIterator for &'a HashMap<K, V, S, A> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Iter<'a, K, V> {
+        self.iter()
+    }
+}
+
+impl<'a, K, V, S, A: Allocator> IntoIterator for &'a mut HashMap<K, V, S, A> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> IterMut<'a, K, V> {
+        self.iter_mut()
+    }
+}
+
+// Unit tests for the `HashMap` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocator::CountingAllocator;
+    use std::collections::HashMap as StdHashMap;
+    use std::hash::{BuildHasherDefault, Hasher};
+    use std::rc::Rc;
+
+    // Hashes every key to the same value, so every entry collides and probes the whole cluster
+    #[derive(Default)]
+    struct CollidingHasher;
+
+    impl Hasher for CollidingHasher {
+        fn finish(&self) -> u64 {
+            7
+        }
+
+        fn write(&mut self, _bytes: &[u8]) {}
+    }
+
+    // FNV-1a, a simple deterministic hasher
+    struct Fnv(u64);
+
+    impl Default for Fnv {
+        fn default() -> Self {
+            Fnv(0xcbf2_9ce4_8422_2325)
+        }
+    }
+
+    impl Hasher for Fnv {
+        fn finish(&self) -> u64 {
+            self.0
+        }
+
+        fn write(&mut self, bytes: &[u8]) {
+            for &byte in bytes {
+                self.0 = (self.0 ^ byte as u64).wrapping_mul(0x100_0000_01b3);
+            }
+        }
+    }
+
+    // Applies the same random operations to a `HashMap` and to the standard one, comparing every result
+    fn differential<S: BuildHasher + Default>(steps: usize, keys: u32) {
+        let mut map: HashMap<u32, u64, S> = HashMap::default();
+        let mut expected = StdHashMap::new();
+        let mut state = 0x2545_f491u32;
+        for step in 0..steps as u64 {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let key = state % keys;
+            match state >> 29 {
+                0..=2 => assert_eq!(map.insert(key, step), expected.insert(key, step)),
+                3 | 4 => assert_eq!(map.remove(&key), expected.remove(&key)),
+                5 => {
+                    *map.entry(key).or_default() += step;
+                    *expected.entry(key).or_default() += step;
+                }
+                6 => {
+                    map.entry(key).and_modify(|value| *value /= 2).or_insert(step);
+                    expected.entry(key).and_modify(|value| *value /= 2).or_insert(step);
+                }
+                _ => assert_eq!(map.get(&key), expected.get(&key)),
+            }
+            assert_eq!(map.size(), expected.len());
+        }
+
+        let mut entries: Vec<(u32, u64)> = map.iter().map(|(&k, &v)| (k, v)).collect();
+        let mut expected_entries: Vec<(u32, u64)> = expected.into_iter().collect();
+        entries.sort();
+        expected_entries.sort();
+        assert_eq!(entries, expected_entries);
+        for key in 0..keys {
+            assert_eq!(map.contains_key(&key), entries.binary_search_by_key(&key, |e| e.0).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_matches_std_hash_map() {
+        differential::<RandomState>(100_000, 5000);
+        differential::<BuildHasherDefault<Fnv>>(100_000, 300);
+    }
+
+    #[test]
+    fn test_matches_std_hash_map_when_every_key_collides() {
+        differential::<BuildHasherDefault<CollidingHasher>>(5000, 100);
+    }
+
+    #[test]
+    fn test_grows_and_shrinks() {
+        let mut map = HashMap::new();
+        assert_eq!(map.capacity(), 16);
+        for i in 0..12 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.capacity(), 16);
+        map.insert(12, 12);
+        assert_eq!(map.capacity(), 32);
+        for i in 0..1000 {
+            map.insert(i, i);
+        }
+        assert_eq!(map.capacity(), 2048);
+        for i in 0..990 {
+            assert_eq!(map.remove(&i), Some(i));
+        }
+        assert_eq!(map.capacity(), 32);
+        assert!((990..1000).all(|i| map[&i] == i));
+        map.clear();
+        assert!(map.is_empty());
+        assert_eq!(map.capacity(), 16);
+    }
+
+    #[test]
+    fn test_slots_from_another_allocator() {
+        let alloc = CountingAllocator::new();
+        let mut map = HashMap::with_hasher_in(RandomState::new(), alloc.clone());
+        for i in 0..100 {
+            map.entry(i).or_insert(i * 2);
+        }
+        for i in 0..90 {
+            map.remove(&i);
+        }
+        assert!((90..100).all(|i| map[&i] == i * 2));
+
+        // Every table the map grew and shrank through came from `alloc` and went back to it
+        assert!(alloc.allocations() > 4);
+        assert_eq!(alloc.allocations(), alloc.deallocations() + 1);
+        let copy = map.clone();
+        assert_eq!(copy, map);
+        drop(map);
+        assert_eq!(copy.into_iter().count(), 10);
+        assert_eq!(alloc.live_bytes(), 0);
+    }
+
+    #[test]
+    fn test_tombstones_are_reused_and_cleared() {
+        // Churning through keys leaves tombstones; the table rebuilds in place instead of growing
+        let mut map = HashMap::new();
+        for i in 0..10_000 {
+            map.insert(i, ());
+            map.remove(&(i - 5).max(0));
+        }
+        assert!(map.capacity() <= 32);
+        assert_eq!(map.size(), 5);
+    }
+
+    #[test]
+    fn test_entry_api() {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for word in "the cat and the hat and the bat".split(' ') {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        assert_eq!(counts["the"], 3);
+        assert_eq!(counts.get("and"), Some(&2));
+        assert_eq!(counts.get("dog"), None);
+
+        match counts.entry("cat") {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.key(), &"cat");
+                assert_eq!(entry.remove(), 1);
+            }
+            Entry::Vacant(_) => panic!("cat should be present"),
+        }
+        assert!(!counts.contains_key("cat"));
+        assert_eq!(counts.entry("cow").key(), &"cow");
+        assert_eq!(*counts.entry("cow").or_insert_with(|| 9), 9);
+    }
+
+    #[test]
+    fn test_iterators_and_traits() {
+        let mut map: HashMap<String, i32> = (0..100).map(|i| (i.to_string(), i)).collect();
+        for (_, value) in map.iter_mut() {
+            *value *= 2;
+        }
+        assert_eq!(map.iter().len(), 100);
+        assert_eq!(map.values().sum::<i32>(), 9900);
+        assert!(map.keys().all(|key| map[key.as_str()] == key.parse::<i32>().unwrap() * 2));
+
+        let copy = map.clone();
+        assert_eq!(copy, map);
+        map.insert("100".to_string(), 0);
+        assert_ne!(copy, map);
+
+        let mut entries: Vec<(String, i32)> = copy.into_iter().collect();
+        entries.sort_by_key(|entry| entry.1);
+        assert_eq!(entries[1], ("1".to_string(), 2));
+        assert_eq!(format!("{:?}", HashMap::<i32, i32>::from_iter([(1, 2)])), "{1: 2}");
+    }
+
+    #[test]
+    fn test_drops_every_entry_once() {
+        let tracker = Rc::new(());
+        let mut map = HashMap::new();
+        for i in 0..100 {
+            map.insert(i, Rc::clone(&tracker));
+        }
+        for i in 0..50 {
+            map.insert(i, Rc::clone(&tracker));
+            map.remove(&(i + 50));
+        }
+        assert_eq!(Rc::strong_count(&tracker), 51);
+        let mut iter = map.into_iter();
+        iter.next();
+        drop(iter);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+}
@@ -0,0 +1,575 @@
+// Priority queue stored as a binary heap in a `Vector`:
+// * push(item) - adds an item in O(log n) and returns a `Handle` to it
+// * pop(), peek(), peek_mut() - take, look at or change the item that comes out first
+// * decrease_key(handle, item) - replaces an item, typically with a more urgent one, and moves it
+//     to its new place in O(log n); remove(handle) takes an item out from anywhere in the heap
+// * From<Vector> - builds a heap from existing elements in O(n)
+// * into_sorted() - the items in the order `pop` would return them
+// * MaxHeap (the default) or MinHeap - whether the largest or the smallest item comes out first
+//
+// Every item has a slot in a side table recording where in the heap it currently is, so a handle
+// finds its item without searching. Slots of items that left the queue are reused, and their
+// generation is bumped so stale handles are recognised. The items can live in a vector from any
+// allocator; the side tables always use the global one.
+
+use crate::allocator::{Allocator, Global};
+use crate::growth::{Doubling, GrowthPolicy};
+use crate::vector::Vector;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+// Decides which of two items leaves the queue first
+pub trait HeapOrder {
+    // Returns true if `a` must come out before `b`
+    fn comes_first<T: Ord>(&self, a: &T, b: &T) -> bool;
+}
+
+// The largest item comes out first (the default)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MaxHeap;
+
+impl HeapOrder for MaxHeap {
+    fn comes_first<T: Ord>(&self, a: &T, b: &T) -> bool {
+        a > b
+    }
+}
+
+// The smallest item comes out first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MinHeap;
+
+impl HeapOrder for MinHeap {
+    fn comes_first<T: Ord>(&self, a: &T, b: &T) -> bool {
+        a < b
+    }
+}
+
+// Identifies an item pushed to a `PriorityQueue` for as long as it stays in the queue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: usize,     // Index in the slot table
+    generation: u32, // Generation of the slot when the item was pushed
+}
+
+// Position of one item in the heap; `VACANT` once the item has left
+#[derive(Debug, Clone)]
+struct Slot {
+    position: usize,
+    generation: u32,
+}
+
+// Position of slots whose item has left the queue
+const VACANT: usize = usize::MAX;
+
+// Define a `PriorityQueue` struct with the heap, the slot of each heap entry and the slot table
+pub struct PriorityQueue<T, O = MaxHeap, P = Doubling, A: Allocator = Global> {
+    items: Vector<T, P, A>, // Items in heap order: nothing comes out before its parent
+    owners: Vector<usize>,  // Slot of the item at each heap position
+    slots: Vector<Slot>,    // Heap position of the item behind each handle
+    free: Vector<usize>,    // Slots available for reuse
+    order: O,               // Which item comes out first
+}
+
+impl<T: Ord> PriorityQueue<T> {
+    // Creates an empty max-heap with an initial capacity, like `Vector::new`
+    pub fn new(initial_capacity: usize) -> Self {
+        Self::with_order(initial_capacity, MaxHeap)
+    }
+}
+
+impl<T: Ord, O: HeapOrder> PriorityQueue<T, O> {
+    // Creates an empty queue with an initial capacity, taking items out in the given order
+    pub fn with_order(initial_capacity: usize, order: O) -> Self {
+        Self::heapify(Vector::new(initial_capacity), order)
+    }
+}
+
+impl<T: Ord, O: HeapOrder, P: GrowthPolicy, A: Allocator> PriorityQueue<T, O, P, A> {
+    // Turns a vector into a queue in O(n) by sifting down every parent, bottom up. The items get
+    // no handles of their own; they can only be reached through `peek` and `pop`.
+    pub fn heapify(items: Vector<T, P, A>, order: O) -> Self {
+        let len = items.size();
+        let mut queue = PriorityQueue {
+            items,
+            owners: (0..len).collect(),
+            slots: (0..len).map(|position| Slot { position, generation: 0 }).collect(),
+            free: Vector::new(0),
+            order,
+        };
+        for parent in (0..len / 2).rev() {
+            queue.sift_down(parent, len);
+        }
+        queue
+    }
+
+    // Returns the number of items in the queue
+    pub fn size(&self) -> usize {
+        self.items.size()
+    }
+
+    // Checks if the queue is empty
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    // Returns the order items come out in
+    pub fn order(&self) -> &O {
+        &self.order
+    }
+
+    // Adds an item and returns a handle to it
+    pub fn push(&mut self, item: T) -> Handle {
+        let position = self.items.size();
+        let slot = self.claim_slot(position);
+        self.items.push(item);
+        self.owners.push(slot);
+        self.sift_up(position);
+        Handle { slot, generation: self.slots[slot].generation }
+    }
+
+    // Removes the item that comes out first and returns it, `None` if empty
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(self.take(0))
+    }
+
+    // Returns the item that comes out first, `None` if empty
+    pub fn peek(&self) -> Option<&T> {
+        self.items.get(0)
+    }
+
+    // Returns the item that comes out first for changing it in place, `None` if empty; the heap
+    // order is restored when the returned guard is dropped
+    pub fn peek_mut(&mut self) -> Option<PeekMut<'_, T, O, P, A>> {
+        if self.is_empty() {
+            return None;
+        }
+        Some(PeekMut { queue: self })
+    }
+
+    // Checks if the item behind a handle is still in the queue
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.position(handle).is_some()
+    }
+
+    // Returns the item behind a handle, `None` if it already left the queue
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.items.get(self.position(handle)?)
+    }
+
+    // Replaces the item behind a handle and moves it to its new place in O(log n), returning the
+    // old item. Usually the new item is more urgent and moves up, but either direction works.
+    // Blows up if the item already left the queue.
+    pub fn decrease_key(&mut self, handle: Handle, item: T) -> T {
+        let Some(position) = self.position(handle) else {
+            panic!("Handle refers to an item that is no longer in the queue");
+        };
+        let old = std::mem::replace(&mut self.items[position], item);
+        let position = self.sift_up(position);
+        self.sift_down(position, self.size());
+        old
+    }
+
+    // Removes the item behind a handle from anywhere in the queue, `None` if it already left
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let position = self.position(handle)?;
+        Some(self.take(position))
+    }
+
+    // Returns the items in heap order, which is not sorted
+    pub fn as_slice(&self) -> &[T] {
+        self.items.as_slice()
+    }
+
+    // Returns an iterator over the items in heap order
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.items.iter()
+    }
+
+    // Returns the items in the order `pop` would return them, sorting in place with heapsort
+    pub fn into_sorted(mut self) -> Vector<T, P, A> {
+        // Each round moves the first item behind the shrinking heap, so the end of the vector
+        // fills up with items in pop order from the back
+        for end in (1..self.size()).rev() {
+            self.swap(0, end);
+            self.sift_down(0, end);
+        }
+        self.items.reverse();
+        self.items
+    }
+
+    // Returns the items in heap order
+    pub fn into_vector(self) -> Vector<T, P, A> {
+        self.items
+    }
+
+    // Returns the heap position of the item behind a handle, if it is still in the queue
+    fn position(&self, handle: Handle) -> Option<usize> {
+        let slot = self.slots.get(handle.slot)?;
+        if slot.generation != handle.generation || slot.position == VACANT {
+            return None;
+        }
+        Some(slot.position)
+    }
+
+    // Removes the item at a heap position, moving the last item into its place
+    fn take(&mut self, position: usize) -> T {
+        let last = self.size() - 1;
+        self.swap(position, last);
+        let item = self.items.pop().unwrap();
+        let slot = self.owners.pop().unwrap();
+        self.release_slot(slot);
+        if position < last {
+            let position = self.sift_up(position);
+            self.sift_down(position, last);
+        }
+        item
+    }
+
+    // Moves the item at `position` up past every parent it comes out before and returns where it ends up
+    fn sift_up(&mut self, mut position: usize) -> usize {
+        while position > 0 {
+            let parent = (position - 1) / 2;
+            if !self.order.comes_first(&self.items[position], &self.items[parent]) {
+                break;
+            }
+            self.swap(position, parent);
+            position = parent;
+        }
+        position
+    }
+
+    // Moves the item at `position` down within the first `end` items until no child comes out before it
+    fn sift_down(&mut self, mut position: usize, end: usize) {
+        loop {
+            let mut child = 2 * position + 1;
+            if child >= end {
+                return;
+            }
+            if child + 1 < end && self.order.comes_first(&self.items[child + 1], &self.items[child]) {
+                child += 1;
+            }
+            if !self.order.comes_first(&self.items[child], &self.items[position]) {
+                return;
+            }
+            self.swap(position, child);
+            position = child;
+        }
+    }
+
+    // Swaps two heap entries and records their new positions
+    fn swap(&mut self, a: usize, b: usize) {
+        self.items.swap(a, b);
+        self.owners.swap(a, b);
+        self.slots[self.owners[a]].position = a;
+        self.slots[self.owners[b]].position = b;
+    }
+
+    // Returns a slot for an item at `position`, reusing a released one if possible
+    fn claim_slot(&mut self, position: usize) -> usize {
+        match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot].position = position;
+                slot
+            }
+            None => {
+                self.slots.push(Slot { position, generation: 0 });
+                self.slots.size() - 1
+            }
+        }
+    }
+
+    // Marks a slot as vacant and invalidates the handles pointing to it
+    fn release_slot(&mut self, slot: usize) {
+        let entry = &mut self.slots[slot];
+        entry.position = VACANT;
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free.push(slot);
+    }
+}
+
+// Define a guard giving mutable access to the first item of a `PriorityQueue`; dropping it moves
+// the item down to where it now belongs
+pub struct PeekMut<'a, T: Ord, O: HeapOrder, P: GrowthPolicy, A: Allocator = Global> {
+    queue: &'a mut PriorityQueue<T, O, P, A>,
+}
+
+impl<T: Ord, O: HeapOrder, P: GrowthPolicy, A: Allocator> PeekMut<'_, T, O, P, A> {
+    // Removes the peeked item from the queue and returns it; dropping the guard afterwards only
+    // sifts down the item that replaced it
+    pub fn pop(this: Self) -> T {
+        this.queue.take(0)
+    }
+}
+
+impl<T: Ord, O: HeapOrder, P: GrowthPolicy, A: Allocator> Deref for PeekMut<'_, T, O, P, A> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.queue.items[0]
+    }
+}
+
+impl<T: Ord, O: HeapOrder, P: GrowthPolicy, A: Allocator> DerefMut for PeekMut<'_, T, O, P, A> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.queue.items[0]
+    }
+}
+
+impl<T: Ord, O: HeapOrder, P: GrowthPolicy, A: Allocator> Drop for PeekMut<'_, T, O, P, A> {
+    fn drop(&mut self) {
+        let size = self.queue.size();
+        self.queue.sift_down(0, size);
+    }
+}
+
+// Builds a max-heap from a vector in O(n)
+impl<T: Ord, P: GrowthPolicy, A: Allocator> From<Vector<T, P, A>> for PriorityQueue<T, MaxHeap, P, A> {
+    fn from(items: Vector<T, P, A>) -> Self {
+        Self::heapify(items, MaxHeap)
+    }
+}
+
+impl<T: Ord, O: HeapOrder + Default, P: GrowthPolicy + Default, A: Allocator + Default> FromIterator<T>
+    for PriorityQueue<T, O, P, A>
+{
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::heapify(iter.into_iter().collect(), O::default())
+    }
+}
+
+impl<T: Ord, O: HeapOrder, P: GrowthPolicy, A: Allocator> Extend<T> for PriorityQueue<T, O, P, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<T: Ord, O: HeapOrder + Default> Default for PriorityQueue<T, O> {
+    fn default() -> Self {
+        Self::with_order(0, O::default())
+    }
+}
+
+impl<T: Ord + Clone, O: HeapOrder + Clone, P: GrowthPolicy + Clone, A: Allocator + Clone> Clone
+    for PriorityQueue<T, O, P, A>
+{
+    fn clone(&self) -> Self {
+        PriorityQueue {
+            items: self.items.clone(),
+            owners: self.owners.clone(),
+            slots: self.slots.clone(),
+            free: self.free.clone(),
+            order: self.order.clone(),
+        }
+    }
+}
+
+impl<T: Ord + fmt::Debug, O: HeapOrder, P: GrowthPolicy, A: Allocator> fmt::Debug for PriorityQueue<T, O, P, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// Unit tests for the `PriorityQueue` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocator::BumpAllocator;
+    use crate::vector;
+    use std::cell::Cell;
+    use std::cmp::Ordering;
+    use std::collections::BinaryHeap;
+
+    fn random_values(seed: u32, len: usize) -> Vec<i32> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as i32 % 1000
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_std_binary_heap() {
+        let mut queue = PriorityQueue::new(0);
+        let mut expected = BinaryHeap::new();
+        for (step, value) in random_values(1, 5000).into_iter().enumerate() {
+            if step % 3 == 2 {
+                assert_eq!(queue.pop(), expected.pop());
+            } else {
+                queue.push(value);
+                expected.push(value);
+            }
+            assert_eq!(queue.peek(), expected.peek());
+            assert_eq!(queue.size(), expected.len());
+        }
+        assert_eq!(queue.into_sorted(), expected.into_sorted_vec().into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_min_heap() {
+        let mut queue = PriorityQueue::with_order(0, MinHeap);
+        for value in [5, 1, 8, 3, 9, 2] {
+            queue.push(value);
+        }
+        assert_eq!(queue.peek(), Some(&1));
+        assert_eq!(queue.into_sorted(), [1, 2, 3, 5, 8, 9]);
+
+        let queue: PriorityQueue<i32, MinHeap> = random_values(2, 300).into_iter().collect();
+        let mut expected = random_values(2, 300);
+        expected.sort();
+        assert_eq!(queue.into_sorted(), expected);
+    }
+
+    #[test]
+    fn test_items_from_another_allocator() {
+        let mut items = Vector::new_in(0, BumpAllocator::new(4096));
+        items.extend_from_slice(&random_values(3, 200));
+        let mut queue = PriorityQueue::heapify(items, MinHeap);
+        let handle = queue.push(-1);
+        queue.decrease_key(handle, i32::MIN);
+
+        let mut expected = random_values(3, 200);
+        expected.push(i32::MIN);
+        expected.sort();
+        let sorted = queue.into_sorted();
+        assert!(sorted.allocator().used() > 0);
+        assert_eq!(sorted, expected);
+    }
+
+    #[test]
+    fn test_heapify_is_linear() {
+        // Counts comparisons, which a linear-time heapify keeps under 2n
+        thread_local!(static COMPARISONS: Cell<usize> = const { Cell::new(0) });
+        #[derive(PartialEq, Eq)]
+        struct Counted(i32);
+        impl PartialOrd for Counted {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Counted {
+            fn cmp(&self, other: &Self) -> Ordering {
+                COMPARISONS.with(|c| c.set(c.get() + 1));
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let items: Vector<Counted> = (0..10_000).map(Counted).collect();
+        let queue = PriorityQueue::from(items);
+        assert!(COMPARISONS.with(|c| c.get()) < 20_000);
+        assert_eq!(queue.peek().map(|c| c.0), Some(9_999));
+    }
+
+    #[test]
+    fn test_peek_mut() {
+        let mut queue = PriorityQueue::from(vector![3, 7, 5]);
+        *queue.peek_mut().unwrap() = 1;
+        assert_eq!(queue.peek(), Some(&5));
+        {
+            let mut top = queue.peek_mut().unwrap();
+            *top += 10;
+        }
+        assert_eq!(queue.peek(), Some(&15));
+        let top = queue.peek_mut().unwrap();
+        assert_eq!(PeekMut::pop(top), 15);
+        assert_eq!(queue.into_sorted(), [3, 1]);
+
+        let mut empty: PriorityQueue<i32> = PriorityQueue::new(0);
+        assert!(empty.peek_mut().is_none());
+    }
+
+    #[test]
+    fn test_decrease_key_and_remove() {
+        let mut queue = PriorityQueue::with_order(0, MinHeap);
+        let handles: Vec<Handle> = [50, 40, 30, 20, 10].into_iter().map(|value| queue.push(value)).collect();
+        assert_eq!(queue.decrease_key(handles[0], 5), 50);
+        assert_eq!(queue.peek(), Some(&5));
+        assert_eq!(queue.decrease_key(handles[4], 45), 10);
+        assert_eq!(queue.get(handles[4]), Some(&45));
+        assert_eq!(queue.remove(handles[2]), Some(30));
+        assert_eq!(queue.remove(handles[2]), None);
+        assert!(!queue.contains(handles[2]));
+        assert_eq!(queue.pop(), Some(5));
+        assert!(!queue.contains(handles[0]));
+
+        // A reused slot does not revive old handles
+        let handle = queue.push(1);
+        assert!(queue.contains(handle));
+        assert_eq!(queue.get(handles[0]), None);
+        assert_eq!(queue.into_sorted(), [1, 20, 40, 45]);
+    }
+
+    #[test]
+    #[should_panic(expected = "no longer in the queue")]
+    fn test_decrease_key_of_popped_item_panics() {
+        let mut queue = PriorityQueue::new(0);
+        let handle = queue.push(1);
+        queue.pop();
+        queue.decrease_key(handle, 2);
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        // Shortest paths on a grid with random weights, checked against Bellman-Ford
+        let size = 12;
+        let weights = random_values(3, size * size);
+        let weight = |node: usize| (weights[node].unsigned_abs() % 9 + 1) as u64;
+        let neighbours = |node: usize| {
+            let (row, col) = (node / size, node % size);
+            let mut next = Vec::new();
+            if row > 0 {
+                next.push(node - size);
+            }
+            if row + 1 < size {
+                next.push(node + size);
+            }
+            if col > 0 {
+                next.push(node - 1);
+            }
+            if col + 1 < size {
+                next.push(node + 1);
+            }
+            next
+        };
+
+        let mut distance = vec![u64::MAX; size * size];
+        let mut handles: Vec<Option<Handle>> = vec![None; size * size];
+        let mut queue = PriorityQueue::with_order(0, MinHeap);
+        distance[0] = 0;
+        handles[0] = Some(queue.push((0, 0)));
+        while let Some((dist, node)) = queue.pop() {
+            for next in neighbours(node) {
+                let candidate = dist + weight(next);
+                if candidate < distance[next] {
+                    distance[next] = candidate;
+                    match handles[next] {
+                        Some(handle) if queue.contains(handle) => {
+                            queue.decrease_key(handle, (candidate, next));
+                        }
+                        _ => handles[next] = Some(queue.push((candidate, next))),
+                    }
+                }
+            }
+        }
+
+        let mut expected = vec![u64::MAX; size * size];
+        expected[0] = 0;
+        for _ in 0..size * size {
+            for node in 0..size * size {
+                if expected[node] == u64::MAX {
+                    continue;
+                }
+                for next in neighbours(node) {
+                    expected[next] = expected[next].min(expected[node] + weight(next));
+                }
+            }
+        }
+        assert_eq!(distance, expected);
+    }
+}
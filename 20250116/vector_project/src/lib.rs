@@ -0,0 +1,50 @@
+// Data structures implemented from scratch on top of raw allocations:
+// * vector - growable array (`Vector`) with pluggable growth policies
+// * growth - growth and shrink policies deciding how a `Vector` changes its capacity
+// * allocator - where a `Vector`'s buffer comes from: the global allocator, a `CountingAllocator` or a `BumpAllocator`
+// * bit_vector - `BitVector`, booleans packed 64 to a word, with rank/select and bitwise operations
+// * deque - ring-buffer `Deque` with O(1) pushes and pops at both ends
+// * hash_map - open-addressing `HashMap` with linear probing and tombstones, on `Vector` storage
+// * heap - binary-heap `PriorityQueue` on `Vector` storage, min- or max-first, with decrease_key
+// * frozen - read-only `FrozenVector` that is cheap to clone and share across threads
+// * binary - compact, checksummed binary format for `Vector<i32>`
+// * concurrent - lock-free, append-only `ConcurrentVector` for many producer threads
+// * persistent - crash-safe `PersistentVector` backed by a snapshot and a write-ahead log
+// * sorted - `SortedVector`, kept in ascending order, with binary search and ordered insert
+// * sort - introsort, merge sort, heapsort and radix sort for `Vector`, in place
+// * simd - SSE2/AVX2 scans over `i32` slices behind `Vector<i32>`'s simd_find, simd_remove, count, minimum, maximum and sum
+//
+// Optional features:
+// * mmap - `mmap_vector`, a file-backed, memory-mapped vector (`MmapVector`)
+// * serde - `Serialize`/`Deserialize` for `Vector`
+// * stats - `stats`, process-wide allocation counters and a hook called on every `Vector` resize
+//
+// Runnable examples live in `examples/`, e.g. `cargo run --example demo`.
+
+pub mod allocator;
+pub mod binary;
+pub mod bit_vector;
+pub mod concurrent;
+pub mod deque;
+pub mod frozen;
+pub mod heap;
+pub mod growth;
+pub mod hash_map;
+pub mod persistent;
+pub mod simd;
+pub mod sort;
+pub mod sorted;
+pub mod vector;
+
+#[cfg(feature = "mmap")]
+pub mod mmap_vector;
+
+#[cfg(feature = "serde")]
+mod serde_support;
+
+#[cfg(feature = "stats")]
+pub mod stats;
+
+pub use allocator::Allocator;
+pub use growth::GrowthPolicy;
+pub use vector::{Vector, VectorError};
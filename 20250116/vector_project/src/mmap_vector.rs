@@ -0,0 +1,400 @@
+// File-backed vector whose buffer lives in a memory-mapped file, enabled by the `mmap` feature.
+// It mirrors the `Vector` API for plain-old-data elements:
+// * open(path) - create the file, or reopen it with the length it had when it was last written
+// * size(), capacity(), is_empty(), at(index), get(index), as_slice(), iter()
+// * push(item), insert(index, item), prepend(item) - grow by extending the file and remapping it,
+//     so they return an `io::Error` if the file cannot grow
+// * pop(), delete(index), remove(item), find(item)
+// * flush() - schedule the changes to be written back; sync() - wait until they are on disk
+//
+// Layout of the file (little-endian):
+//     magic         4 bytes   b"VECM"
+//     version       1 byte    currently 1
+//     element size  1 byte    size_of::<T>(), checked on reopen
+//     reserved      2 bytes
+//     length        8 bytes   number of elements
+//     elements      capacity * size_of::<T>() bytes, the first `length` of them in use
+//
+// The capacity follows the same power-of-two rules as `Vector`. The mapping assumes no other
+// process or mapping modifies the file while it is open.
+
+use crate::growth::{Doubling, GrowthPolicy, MIN_CAPACITY};
+use crate::vector::VectorError;
+use memmap2::MmapMut;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::path::Path;
+use std::slice;
+
+// Bytes every file starts with
+const MAGIC: [u8; 4] = *b"VECM";
+
+// Version written by `open` and the only one it accepts
+const VERSION: u8 = 1;
+
+// Size of the header in front of the elements; a multiple of every element's alignment
+const HEADER_SIZE: usize = 16;
+
+// Byte range of the length field in the header
+const LENGTH_FIELD: std::ops::Range<usize> = 8..16;
+
+mod sealed {
+    pub trait Sealed {}
+}
+
+// Element types that can be stored in a file as raw bytes and read back: fixed-size numbers with
+// no padding, no pointers and no invalid bit patterns
+pub trait FileElement: Copy + PartialEq + sealed::Sealed {}
+
+macro_rules! file_elements {
+    ($($ty:ty),*) => {
+        $(
+            impl sealed::Sealed for $ty {}
+            impl FileElement for $ty {}
+        )*
+    };
+}
+
+file_elements!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+// Define a `MmapVector` struct with the backing file, its mapping, size, and capacity
+pub struct MmapVector<T: FileElement> {
+    file: File,      // Backing file, kept open to grow and sync it
+    map: MmapMut,    // Writable shared mapping of the whole file
+    size: usize,     // Current number of elements, mirrored in the header
+    capacity: usize, // Number of elements the file has room for
+    _marker: PhantomData<T>,
+}
+
+impl<T: FileElement> MmapVector<T> {
+    // Opens the vector stored at `path`, creating an empty one with a capacity of 16 if the file
+    // does not exist or is empty
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let file_len = file.metadata()?.len();
+
+        if file_len == 0 {
+            file.set_len(Self::file_len(MIN_CAPACITY)?)?;
+            let mut vec = Self::map(file, MIN_CAPACITY, 0)?;
+            vec.map[..4].copy_from_slice(&MAGIC);
+            vec.map[4] = VERSION;
+            vec.map[5] = mem::size_of::<T>() as u8;
+            vec.set_size(0);
+            return Ok(vec);
+        }
+
+        // Check the header before trusting the length stored in it
+        let file_len = usize::try_from(file_len).map_err(|_| invalid_data("File is too large to map"))?;
+        if file_len < HEADER_SIZE || !(file_len - HEADER_SIZE).is_multiple_of(mem::size_of::<T>()) {
+            return Err(invalid_data("File size does not match the element size"));
+        }
+        let capacity = (file_len - HEADER_SIZE) / mem::size_of::<T>();
+        let mut vec = Self::map(file, capacity, 0)?;
+        if vec.map[..4] != MAGIC {
+            return Err(invalid_data("Not a memory-mapped vector file"));
+        }
+        if vec.map[4] != VERSION {
+            return Err(invalid_data("Unsupported memory-mapped vector version"));
+        }
+        if vec.map[5] as usize != mem::size_of::<T>() {
+            return Err(invalid_data("File was written with a different element size"));
+        }
+        let size = u64::from_le_bytes(vec.map[LENGTH_FIELD].try_into().unwrap());
+        vec.size = usize::try_from(size)
+            .ok()
+            .filter(|&size| size <= capacity)
+            .ok_or_else(|| invalid_data("Stored length exceeds the file size"))?;
+        Ok(vec)
+    }
+
+    // Returns the current number of elements in the vector
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Returns the number of elements the file has room for
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    // Checks if the vector is empty (i.e., has no elements)
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    // Returns the element at a given index, panics if the index is out of bounds
+    pub fn at(&self, index: usize) -> T {
+        match self.get(index) {
+            Some(item) => item,
+            None => panic!("{}", VectorError::IndexOutOfBounds { index, len: self.size }),
+        }
+    }
+
+    // Returns the element at a given index, or `None` if the index is out of bounds
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.as_slice().get(index).copied()
+    }
+
+    // Returns the elements as a slice of the mapped file
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.data(), self.size) }
+    }
+
+    // Returns the elements as a mutable slice of the mapped file
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.data_mut(), self.size) }
+    }
+
+    // Returns an iterator over the elements, front to back
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    // Adds a new element to the end of the vector, growing the file if necessary
+    pub fn push(&mut self, item: T) -> io::Result<()> {
+        if self.size == self.capacity {
+            self.grow()?;
+        }
+        unsafe { self.data_mut().add(self.size).write(item) };
+        self.set_size(self.size + 1);
+        Ok(())
+    }
+
+    // Inserts an element at a specified index, shifting existing elements; panics if the index
+    // is out of bounds
+    pub fn insert(&mut self, index: usize, item: T) -> io::Result<()> {
+        if index >= self.size {
+            panic!("{}", VectorError::IndexOutOfBounds { index, len: self.size });
+        }
+        if self.size == self.capacity {
+            self.grow()?;
+        }
+        self.set_size(self.size + 1);
+        let slice = self.as_mut_slice();
+        slice.copy_within(index..slice.len() - 1, index + 1);
+        slice[index] = item;
+        Ok(())
+    }
+
+    // Inserts an element at the beginning of the vector
+    pub fn prepend(&mut self, item: T) -> io::Result<()> {
+        self.insert(0, item)
+    }
+
+    // Removes and returns the last element, shrinking the file like `Vector::pop` shrinks its buffer
+    pub fn pop(&mut self) -> Option<T> {
+        let value = *self.as_slice().last()?;
+        self.set_size(self.size - 1);
+        self.shrink();
+        Some(value)
+    }
+
+    // Deletes the element at a specified index and shifts the remaining elements; panics if the
+    // index is out of bounds
+    pub fn delete(&mut self, index: usize) {
+        if index >= self.size {
+            panic!("{}", VectorError::IndexOutOfBounds { index, len: self.size });
+        }
+        self.as_mut_slice().copy_within(index + 1.., index);
+        self.set_size(self.size - 1);
+        self.shrink();
+    }
+
+    // Removes all occurrences of a specified item from the vector in a single pass
+    pub fn remove(&mut self, item: &T) {
+        let slice = self.as_mut_slice();
+        let mut kept = 0;
+        for i in 0..slice.len() {
+            if slice[i] != *item {
+                slice[kept] = slice[i];
+                kept += 1;
+            }
+        }
+        self.set_size(kept);
+        self.shrink();
+    }
+
+    // Finds the index of the first occurrence of an item, returns `None` if not found
+    pub fn find(&self, item: &T) -> Option<usize> {
+        self.iter().position(|x| x == item)
+    }
+
+    // Starts writing the changes back to the file without waiting for them to reach the disk
+    pub fn flush(&self) -> io::Result<()> {
+        self.map.flush_async()
+    }
+
+    // Writes the changes back to the file and waits until they and the file size are on disk
+    pub fn sync(&self) -> io::Result<()> {
+        self.map.flush()?;
+        self.file.sync_all()
+    }
+
+    // Pointer to the first element, right after the header, for reading
+    fn data(&self) -> *const T {
+        unsafe { self.map.as_ptr().add(HEADER_SIZE) as *const T }
+    }
+
+    // Pointer to the first element, right after the header, for writing
+    fn data_mut(&mut self) -> *mut T {
+        unsafe { self.map.as_mut_ptr().add(HEADER_SIZE) as *mut T }
+    }
+
+    // Updates the size and the length stored in the header
+    fn set_size(&mut self, size: usize) {
+        self.size = size;
+        self.map[LENGTH_FIELD].copy_from_slice(&(size as u64).to_le_bytes());
+    }
+
+    // Grows the file to the next capacity chosen by the default growth policy and remaps it
+    fn grow(&mut self) -> io::Result<()> {
+        let capacity = Doubling.grow(self.capacity, self.size + 1).ok_or_else(capacity_overflow)?;
+        self.remap(capacity)
+    }
+
+    // Shrinks the file like `Vector` shrinks its buffer; shrinking is only an optimization,
+    // so keep the larger file if it fails
+    fn shrink(&mut self) {
+        if let Some(capacity) = Doubling.shrink(self.size, self.capacity) {
+            let _ = self.remap(capacity);
+        }
+    }
+
+    // Resizes the file to hold `capacity` elements and maps it again; the elements stay in place
+    // in the file, so nothing is copied
+    fn remap(&mut self, capacity: usize) -> io::Result<()> {
+        let old_len = Self::file_len(self.capacity)?;
+        self.file.set_len(Self::file_len(capacity)?)?;
+        match unsafe { MmapMut::map_mut(&self.file) } {
+            Ok(map) => {
+                self.map = map;
+                self.capacity = capacity;
+                Ok(())
+            }
+            Err(err) => {
+                // Keep the old mapping usable by restoring the file size it covers
+                let _ = self.file.set_len(old_len);
+                Err(err)
+            }
+        }
+    }
+
+    // Maps an open file holding `capacity` elements
+    fn map(file: File, capacity: usize, size: usize) -> io::Result<Self> {
+        let map = unsafe { MmapMut::map_mut(&file)? };
+        Ok(MmapVector { file, map, size, capacity, _marker: PhantomData })
+    }
+
+    // Size of a file holding `capacity` elements
+    fn file_len(capacity: usize) -> io::Result<u64> {
+        capacity
+            .checked_mul(mem::size_of::<T>())
+            .and_then(|bytes| bytes.checked_add(HEADER_SIZE))
+            .map(|len| len as u64)
+            .ok_or_else(capacity_overflow)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn capacity_overflow() -> io::Error {
+    io::Error::new(io::ErrorKind::OutOfMemory, VectorError::CapacityOverflow)
+}
+
+// Unit tests for the `MmapVector` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mmap_vector_operations() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = MmapVector::<i32>::open(dir.path().join("vec")).unwrap();
+        assert_eq!(vec.size(), 0);
+        assert_eq!(vec.capacity(), 16);
+
+        vec.push(10).unwrap();
+        vec.push(20).unwrap();
+        vec.insert(1, 15).unwrap();
+        vec.prepend(5).unwrap();
+        assert_eq!(vec.as_slice(), &[5, 10, 15, 20]);
+        assert_eq!(vec.pop(), Some(20));
+        vec.delete(0);
+        assert_eq!(vec.find(&15), Some(1));
+        vec.remove(&15);
+        assert_eq!(vec.find(&15), None);
+        assert_eq!(vec.as_slice(), &[10]);
+        assert_eq!(vec.at(0), 10);
+        assert_eq!(vec.get(1), None);
+    }
+
+    #[test]
+    fn test_growth_extends_and_remaps_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vec");
+        let mut vec = MmapVector::<u64>::open(&path).unwrap();
+        for i in 0..1000 {
+            vec.push(i).unwrap();
+        }
+        assert_eq!(vec.capacity(), 1024);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 16 + 1024 * 8);
+        assert!(vec.iter().copied().eq(0..1000));
+
+        // Popping shrinks the file again
+        while vec.size() > 10 {
+            vec.pop();
+        }
+        assert_eq!(vec.capacity(), 32);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 16 + 32 * 8);
+        assert!(vec.iter().copied().eq(0..10));
+    }
+
+    #[test]
+    fn test_reopen_keeps_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vec");
+        {
+            let mut vec = MmapVector::<i32>::open(&path).unwrap();
+            for i in 0..100 {
+                vec.push(-i).unwrap();
+            }
+            vec.delete(0);
+            vec.sync().unwrap();
+        }
+
+        let mut vec = MmapVector::<i32>::open(&path).unwrap();
+        assert_eq!(vec.size(), 99);
+        assert_eq!(vec.capacity(), 128);
+        assert!(vec.iter().copied().eq((1..100).map(|i| -i)));
+        vec.push(7).unwrap();
+        vec.flush().unwrap();
+        drop(vec);
+        assert_eq!(MmapVector::<i32>::open(&path).unwrap().size(), 100);
+    }
+
+    #[test]
+    fn test_rejects_foreign_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vec");
+
+        std::fs::write(&path, b"not a vector file").unwrap();
+        assert_eq!(MmapVector::<u8>::open(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+
+        // A file written with one element type cannot be opened with another of a different size
+        MmapVector::<i32>::open(dir.path().join("ints")).unwrap().push(1).unwrap();
+        let err = MmapVector::<i64>::open(dir.path().join("ints")).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // A corrupt length is rejected instead of reading past the mapping
+        let path = dir.path().join("corrupt");
+        MmapVector::<i32>::open(&path).unwrap();
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes[LENGTH_FIELD].copy_from_slice(&1000u64.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        assert_eq!(MmapVector::<i32>::open(&path).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,725 @@
+// Crash-safe vector of `i32` kept in a directory as a snapshot plus a write-ahead log:
+// * open(dir) - load the snapshot, replay the log on top of it and drop a torn last record
+// * push(item), insert(index, item), prepend(item), pop(), delete(index), remove(item) - append the
+//     mutation to the log first, then apply it to the in-memory `Vector`
+// * compact() - write the whole vector as a new snapshot and start an empty log; happens
+//     automatically every `compaction_threshold` records, and an automatic compaction that fails
+//     is retried on the next mutation instead of failing the mutation that triggered it
+// * sync() - wait until the log has reached the disk
+//
+// Files in the directory:
+//     snapshot   generation (u64 LE) followed by the vector in the `binary` format
+//     wal        magic b"VECW", version byte, generation (u64 LE), then records
+//
+// A record is an opcode byte, its varint arguments (elements zigzag-encoded like in `binary`) and
+// a CRC-32 of those bytes. The log is replayed only if its generation matches the snapshot's,
+// so a crash between writing a snapshot and resetting the log never applies a record twice.
+// Records are handed to the operating system before the mutation is applied, which survives a
+// crash of the process; call `sync` as well to survive a crash of the machine.
+
+use crate::binary::{self, read_varint, write_varint, zigzag_decode, zigzag_encode, Crc32, FormatError};
+use crate::vector::{Vector, VectorError};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+// Bytes every log starts with
+const LOG_MAGIC: [u8; 4] = *b"VECW";
+
+// Version written to the log header and the only one `open` accepts
+const LOG_VERSION: u8 = 1;
+
+// Size of the log header: magic, version and generation
+const LOG_HEADER_SIZE: usize = 13;
+
+// Number of records after which the log is compacted into a snapshot by default
+const DEFAULT_COMPACTION_THRESHOLD: usize = 1024;
+
+const SNAPSHOT_FILE: &str = "snapshot";
+const SNAPSHOT_TEMP_FILE: &str = "snapshot.tmp";
+const LOG_FILE: &str = "wal";
+
+// A mutation as it is stored in the log
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Record {
+    Push(i32),
+    Insert(usize, i32),
+    Pop,
+    Delete(usize),
+    Remove(i32),
+}
+
+impl Record {
+    const PUSH: u8 = 1;
+    const INSERT: u8 = 2;
+    const POP: u8 = 3;
+    const DELETE: u8 = 4;
+    const REMOVE: u8 = 5;
+
+    // Encodes the record followed by its checksum
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16);
+        let result = match *self {
+            Record::Push(item) => {
+                bytes.push(Record::PUSH);
+                write_varint(&mut bytes, zigzag_encode(item) as u64)
+            }
+            Record::Insert(index, item) => {
+                bytes.push(Record::INSERT);
+                write_varint(&mut bytes, index as u64)
+                    .and_then(|_| write_varint(&mut bytes, zigzag_encode(item) as u64))
+            }
+            Record::Pop => {
+                bytes.push(Record::POP);
+                Ok(())
+            }
+            Record::Delete(index) => {
+                bytes.push(Record::DELETE);
+                write_varint(&mut bytes, index as u64)
+            }
+            Record::Remove(item) => {
+                bytes.push(Record::REMOVE);
+                write_varint(&mut bytes, zigzag_encode(item) as u64)
+            }
+        };
+        result.expect("writing to a Vec<u8> cannot fail");
+
+        let mut crc = Crc32::new();
+        crc.update(&bytes);
+        bytes.extend(crc.finish().to_le_bytes());
+        bytes
+    }
+
+    // Decodes the record at the start of `bytes` and advances past it; `None` means the bytes do
+    // not hold a complete, intact record, which is how a torn write at the end of the log looks,
+    // and leaves `bytes` where it was
+    fn decode(bytes: &mut &[u8]) -> Option<Record> {
+        let start = *bytes;
+        let mut input = start;
+        let opcode = *input.first()?;
+        input = &input[1..];
+
+        let item = |input: &mut &[u8]| -> Result<i32, FormatError> {
+            let value = u32::try_from(read_varint(input)?).map_err(|_| FormatError::MalformedVarint)?;
+            Ok(zigzag_decode(value))
+        };
+        let index = |input: &mut &[u8]| -> Result<usize, FormatError> {
+            usize::try_from(read_varint(input)?).map_err(|_| FormatError::MalformedVarint)
+        };
+        let record = match opcode {
+            Record::PUSH => Record::Push(item(&mut input).ok()?),
+            Record::INSERT => {
+                let index = index(&mut input).ok()?;
+                Record::Insert(index, item(&mut input).ok()?)
+            }
+            Record::POP => Record::Pop,
+            Record::DELETE => Record::Delete(index(&mut input).ok()?),
+            Record::REMOVE => Record::Remove(item(&mut input).ok()?),
+            _ => return None,
+        };
+
+        let body = &start[..start.len() - input.len()];
+        let stored: [u8; 4] = input.get(..4)?.try_into().ok()?;
+        input = &input[4..];
+        let mut crc = Crc32::new();
+        crc.update(body);
+        if crc.finish() != u32::from_le_bytes(stored) {
+            return None;
+        }
+        *bytes = input;
+        Some(record)
+    }
+
+    // Applies the record to a vector, failing if it does not fit the vector's current state
+    fn apply(&self, vec: &mut Vector<i32>) -> Result<(), VectorError> {
+        match *self {
+            Record::Push(item) => vec.try_push(item),
+            Record::Insert(index, item) => vec.try_insert(index, item),
+            Record::Pop => vec.pop().map(|_| ()).ok_or(VectorError::IndexOutOfBounds { index: 0, len: 0 }),
+            Record::Delete(index) => vec.try_delete(index).map(|_| ()),
+            Record::Remove(item) => {
+                vec.simd_remove(&item);
+                Ok(())
+            }
+        }
+    }
+}
+
+// Define a `PersistentVector` struct with the in-memory vector and the files that make it durable
+pub struct PersistentVector {
+    dir: PathBuf,                // Directory holding the snapshot and the log
+    vec: Vector<i32>,            // Current contents, snapshot plus every logged record
+    log: Box<dyn LogFile>,       // Log opened for appending
+    generation: u64,             // Generation shared by the current snapshot and log
+    records: usize,              // Number of records in the log
+    log_len: u64,                // Length of the log up to the end of its last intact record
+    poisoned: bool,              // A torn record could not be cut off the log, so nothing may follow it
+    log_stale: bool,             // The log still belongs to an older snapshot and must be restarted
+    compaction_threshold: usize, // Number of records after which the log is compacted
+}
+
+impl PersistentVector {
+    // Opens the vector stored in `dir`, creating the directory and an empty vector if needed.
+    // A torn or corrupt record ends the log: it and everything after it are discarded.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+
+        // Load the snapshot, if one was ever written
+        let (generation, mut vec) = match fs::read(dir.join(SNAPSHOT_FILE)) {
+            Ok(bytes) => {
+                let generation = bytes.get(..8).ok_or_else(|| invalid_data(FormatError::Truncated))?;
+                let generation = u64::from_le_bytes(generation.try_into().unwrap());
+                (generation, binary::read(&bytes[8..]).map_err(invalid_data)?)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (0, Vector::new(0)),
+            Err(err) => return Err(err),
+        };
+
+        // Replay the log if it belongs to this snapshot; a log with a torn header or an older
+        // generation holds nothing the snapshot does not already contain
+        let bytes = match fs::read(dir.join(LOG_FILE)) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err),
+        };
+        let mut records = 0;
+        let mut valid_len = 0;
+        if bytes.len() >= LOG_HEADER_SIZE && bytes[..4] == LOG_MAGIC {
+            if bytes[4] != LOG_VERSION {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Unsupported log version"));
+            }
+            let log_generation = u64::from_le_bytes(bytes[5..LOG_HEADER_SIZE].try_into().unwrap());
+            if log_generation == generation {
+                let mut input = &bytes[LOG_HEADER_SIZE..];
+                while let Some(record) = Record::decode(&mut input) {
+                    record.apply(&mut vec).map_err(invalid_data)?;
+                    records += 1;
+                }
+                valid_len = bytes.len() - input.len();
+            }
+        }
+
+        // Cut off the torn tail, or start a fresh log, so new records follow the last intact one
+        let mut log = OpenOptions::new().create(true).write(true).truncate(false).open(dir.join(LOG_FILE))?;
+        if valid_len == 0 {
+            Self::reset_log(&mut log, generation)?;
+            valid_len = LOG_HEADER_SIZE;
+        } else {
+            log.set_len(valid_len as u64)?;
+            log.sync_data()?;
+        }
+        let log = Box::new(OpenOptions::new().append(true).open(dir.join(LOG_FILE))?);
+
+        Ok(PersistentVector {
+            dir,
+            vec,
+            log,
+            generation,
+            records,
+            log_len: valid_len as u64,
+            poisoned: false,
+            log_stale: false,
+            compaction_threshold: DEFAULT_COMPACTION_THRESHOLD,
+        })
+    }
+
+    // Returns the current contents
+    pub fn as_vector(&self) -> &Vector<i32> {
+        &self.vec
+    }
+
+    // Returns the current contents as a slice
+    pub fn as_slice(&self) -> &[i32] {
+        self.vec.as_slice()
+    }
+
+    // Returns the current number of elements in the vector
+    pub fn size(&self) -> usize {
+        self.vec.size()
+    }
+
+    // Checks if the vector is empty (i.e., has no elements)
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    // Returns the element at a given index, panics if the index is out of bounds
+    pub fn at(&self, index: usize) -> i32 {
+        self.vec.at(index)
+    }
+
+    // Finds the index of the first occurrence of an item, returns `None` if not found
+    pub fn find(&self, item: i32) -> Option<usize> {
+        self.vec.simd_find(&item)
+    }
+
+    // Returns the number of records written since the last snapshot
+    pub fn log_records(&self) -> usize {
+        self.records
+    }
+
+    // Sets the number of records after which the log is compacted automatically; 0 disables it
+    pub fn set_compaction_threshold(&mut self, records: usize) {
+        self.compaction_threshold = records;
+    }
+
+    // Adds a new element to the end of the vector
+    pub fn push(&mut self, item: i32) -> io::Result<()> {
+        self.commit(Record::Push(item))
+    }
+
+    // Inserts an element at a specified index, shifting existing elements; panics if the index
+    // is out of bounds, before anything is logged
+    pub fn insert(&mut self, index: usize, item: i32) -> io::Result<()> {
+        self.check_index(index);
+        self.commit(Record::Insert(index, item))
+    }
+
+    // Inserts an element at the beginning of the vector
+    pub fn prepend(&mut self, item: i32) -> io::Result<()> {
+        self.insert(0, item)
+    }
+
+    // Removes and returns the last element; nothing is logged if the vector is empty
+    pub fn pop(&mut self) -> io::Result<Option<i32>> {
+        let Some(&last) = self.vec.last() else {
+            return Ok(None);
+        };
+        self.commit(Record::Pop)?;
+        Ok(Some(last))
+    }
+
+    // Deletes the element at a specified index and shifts the remaining elements; panics if the
+    // index is out of bounds, before anything is logged
+    pub fn delete(&mut self, index: usize) -> io::Result<()> {
+        self.check_index(index);
+        self.commit(Record::Delete(index))
+    }
+
+    // Removes all occurrences of a specified item from the vector
+    pub fn remove(&mut self, item: i32) -> io::Result<()> {
+        self.commit(Record::Remove(item))
+    }
+
+    // Waits until every logged record has reached the disk
+    pub fn sync(&self) -> io::Result<()> {
+        self.log.sync_data()
+    }
+
+    // Writes the current contents as a new snapshot and starts an empty log. The snapshot is
+    // written to a temporary file and renamed into place, so a crash leaves either the old
+    // snapshot with its log or the new snapshot.
+    pub fn compact(&mut self) -> io::Result<()> {
+        let generation = self.generation + 1;
+
+        let temp_path = self.dir.join(SNAPSHOT_TEMP_FILE);
+        let mut bytes = generation.to_le_bytes().to_vec();
+        binary::write(&self.vec, &mut bytes).map_err(invalid_data)?;
+        let mut temp = File::create(&temp_path)?;
+        temp.write_all(&bytes)?;
+        temp.sync_all()?;
+        fs::rename(&temp_path, self.dir.join(SNAPSHOT_FILE))?;
+
+        // From here on the new snapshot is the one `open` loads and the old log is stale, even if
+        // a step below fails; records may only be logged again once the log is restarted
+        self.generation = generation;
+        self.records = 0;
+        self.log_stale = true;
+        self.poisoned = false;
+        sync_dir(&self.dir)?;
+        self.restart_log()
+    }
+
+    // Replaces the log with an empty one for the current generation and reopens it for appending
+    fn restart_log(&mut self) -> io::Result<()> {
+        let path = self.dir.join(LOG_FILE);
+        let mut log = OpenOptions::new().create(true).write(true).truncate(false).open(&path)?;
+        Self::reset_log(&mut log, self.generation)?;
+        self.log = Box::new(OpenOptions::new().append(true).open(&path)?);
+        self.log_len = LOG_HEADER_SIZE as u64;
+        self.log_stale = false;
+        Ok(())
+    }
+
+    // Logs a record, applies it and compacts the log if it has grown past the threshold. A failed
+    // write or apply leaves the vector untouched and cuts the part of the record that did reach
+    // the log off again, so that the records after it are replayed and it is not; if even that
+    // fails, every later mutation fails too, until a compaction replaces the log.
+    fn commit(&mut self, record: Record) -> io::Result<()> {
+        if self.poisoned {
+            return Err(io::Error::other("The log ends in a torn record; compact or reopen the vector"));
+        }
+        if self.log_stale {
+            self.restart_log()?;
+        }
+        // Make room before logging, so that a push or insert cannot fail to apply for lack of memory
+        if let Record::Push(_) | Record::Insert(..) = record {
+            self.vec.try_reserve(1).map_err(io::Error::other)?;
+        }
+        let bytes = record.encode();
+        if let Err(err) = self.log.write_all(&bytes) {
+            self.cut_log();
+            return Err(err);
+        }
+        if let Err(err) = record.apply(&mut self.vec) {
+            self.cut_log();
+            return Err(invalid_data(err));
+        }
+        self.log_len += bytes.len() as u64;
+        self.records += 1;
+        if self.compaction_threshold > 0 && self.records >= self.compaction_threshold {
+            // The mutation is logged and applied, so failing it now would invite a retry that
+            // applies it twice; the compaction is tried again on the next mutation instead
+            let _ = self.compact();
+        }
+        Ok(())
+    }
+
+    // Cuts the log back to its last intact record, poisoning it if that fails
+    fn cut_log(&mut self) {
+        if self.log.set_len(self.log_len).is_err() {
+            self.poisoned = true;
+        }
+    }
+
+    // Panics like `Vector` does if an index does not refer to an element
+    fn check_index(&self, index: usize) {
+        if index >= self.vec.size() {
+            panic!("{}", VectorError::IndexOutOfBounds { index, len: self.vec.size() });
+        }
+    }
+
+    // Truncates the log to just a header for `generation`
+    fn reset_log(log: &mut File, generation: u64) -> io::Result<()> {
+        let mut header = Vec::with_capacity(LOG_HEADER_SIZE);
+        header.extend(LOG_MAGIC);
+        header.push(LOG_VERSION);
+        header.extend(generation.to_le_bytes());
+        log.set_len(0)?;
+        log.write_all(&header)?;
+        log.sync_data()
+    }
+}
+
+// File the log is appended to; tests put one in place that fails on demand
+trait LogFile: Write + Send + Sync {
+    // Cuts the file back to `len` bytes
+    fn set_len(&self, len: u64) -> io::Result<()>;
+
+    // Waits until the written data has reached the disk
+    fn sync_data(&self) -> io::Result<()>;
+}
+
+impl LogFile for File {
+    fn set_len(&self, len: u64) -> io::Result<()> {
+        File::set_len(self, len)
+    }
+
+    fn sync_data(&self) -> io::Result<()> {
+        File::sync_data(self)
+    }
+}
+
+fn invalid_data<E: Into<Box<dyn std::error::Error + Send + Sync>>>(err: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+// Makes a rename inside `dir` durable; directories cannot be synced on every platform
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    if cfg!(unix) {
+        File::open(dir)?.sync_all()?;
+    }
+    Ok(())
+}
+
+// Unit tests for the `PersistentVector` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Log file whose next write stops after `short_write` bytes and fails, and whose truncations
+    // fail if `fail_truncate` is set
+    struct FaultyLog {
+        file: File,
+        short_write: Option<usize>,
+        fail_truncate: bool,
+    }
+
+    impl FaultyLog {
+        // Swaps the log of `vec`, stored in `dir`, for a faulty one
+        fn install(vec: &mut PersistentVector, dir: &Path, short_write: Option<usize>, fail_truncate: bool) {
+            let file = OpenOptions::new().append(true).open(dir.join(LOG_FILE)).unwrap();
+            vec.log = Box::new(FaultyLog { file, short_write, fail_truncate });
+        }
+    }
+
+    impl Write for FaultyLog {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if let Some(written) = self.short_write.take() {
+                self.file.write_all(&buf[..written.min(buf.len())])?;
+                return Err(io::Error::new(io::ErrorKind::WriteZero, "Injected short write"));
+            }
+            self.file.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.file.flush()
+        }
+    }
+
+    impl LogFile for FaultyLog {
+        fn set_len(&self, len: u64) -> io::Result<()> {
+            if self.fail_truncate {
+                return Err(io::Error::other("Injected truncation failure"));
+            }
+            self.file.set_len(len)
+        }
+
+        fn sync_data(&self) -> io::Result<()> {
+            self.file.sync_data()
+        }
+    }
+
+    #[test]
+    fn test_record_encoding() {
+        let records = [
+            Record::Push(-5),
+            Record::Insert(3, i32::MAX),
+            Record::Pop,
+            Record::Delete(1 << 40),
+            Record::Remove(i32::MIN),
+        ];
+        let bytes: Vec<u8> = records.iter().flat_map(Record::encode).collect();
+        let mut input = &bytes[..];
+        for record in records {
+            assert_eq!(Record::decode(&mut input), Some(record));
+        }
+        assert!(input.is_empty());
+
+        // Every strict prefix and any flipped bit is rejected
+        let bytes = Record::Insert(7, 1000).encode();
+        for len in 0..bytes.len() {
+            assert_eq!(Record::decode(&mut &bytes[..len]), None);
+        }
+        let mut corrupt = bytes.clone();
+        corrupt[2] ^= 0x10;
+        assert_eq!(Record::decode(&mut &corrupt[..]), None);
+    }
+
+    #[test]
+    fn test_reopen_replays_log() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let mut vec = PersistentVector::open(dir.path()).unwrap();
+            vec.push(10).unwrap();
+            vec.push(20).unwrap();
+            vec.insert(1, 15).unwrap();
+            vec.prepend(5).unwrap();
+            assert_eq!(vec.pop().unwrap(), Some(20));
+            vec.delete(0).unwrap();
+            vec.push(15).unwrap();
+            vec.remove(10).unwrap();
+            assert_eq!(vec.as_slice(), &[15, 15]);
+        }
+
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        assert_eq!(vec.as_slice(), &[15, 15]);
+        assert_eq!(vec.log_records(), 8);
+        vec.push(1).unwrap();
+        drop(vec);
+        assert_eq!(PersistentVector::open(dir.path()).unwrap().as_slice(), &[15, 15, 1]);
+    }
+
+    #[test]
+    fn test_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.set_compaction_threshold(10);
+        for i in 0..25 {
+            vec.push(i).unwrap();
+        }
+        assert_eq!(vec.log_records(), 5);
+        vec.delete(0).unwrap();
+        drop(vec);
+
+        let vec = PersistentVector::open(dir.path()).unwrap();
+        assert!(vec.as_slice().iter().copied().eq(1..25));
+        assert_eq!(vec.log_records(), 6);
+    }
+
+    #[test]
+    fn test_stale_log_after_crash_during_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.set_compaction_threshold(0);
+        vec.push(1).unwrap();
+        vec.push(2).unwrap();
+        let old_log = fs::read(dir.path().join(LOG_FILE)).unwrap();
+        vec.compact().unwrap();
+        drop(vec);
+
+        // Simulate a crash after the snapshot was renamed into place but before the log was reset
+        fs::write(dir.path().join(LOG_FILE), old_log).unwrap();
+        let vec = PersistentVector::open(dir.path()).unwrap();
+        assert_eq!(vec.as_slice(), &[1, 2]);
+        assert_eq!(vec.log_records(), 0);
+    }
+
+    #[test]
+    fn test_recovers_from_log_truncated_at_any_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join(LOG_FILE);
+
+        // Record the expected contents after every logged mutation, and where that record ends
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.set_compaction_threshold(0);
+        vec.push(100).unwrap();
+        vec.compact().unwrap();
+        let mut checkpoints = vec![(fs::metadata(&log_path).unwrap().len(), vec.as_slice().to_vec())];
+        type Mutation<'a> = &'a dyn Fn(&mut PersistentVector) -> io::Result<()>;
+        let mutations: [Mutation; 7] = [
+            &|v| v.push(-1),
+            &|v| v.push(300_000),
+            &|v| v.insert(0, 7),
+            &|v| v.pop().map(|_| ()),
+            &|v| v.delete(1),
+            &|v| v.push(7),
+            &|v| v.remove(7),
+        ];
+        for mutate in mutations {
+            mutate(&mut vec).unwrap();
+            checkpoints.push((fs::metadata(&log_path).unwrap().len(), vec.as_slice().to_vec()));
+        }
+        drop(vec);
+        let full_log = fs::read(&log_path).unwrap();
+
+        for len in 0..=full_log.len() {
+            fs::write(&log_path, &full_log[..len]).unwrap();
+            let mut vec = PersistentVector::open(dir.path()).unwrap();
+            // A log cut inside its header holds nothing beyond the snapshot
+            let expected = &checkpoints.iter().rev().find(|(end, _)| *end as usize <= len).unwrap_or(&checkpoints[0]).1;
+            assert_eq!(vec.as_slice(), &expected[..], "log truncated to {} bytes", len);
+
+            // The torn tail is gone, so new records are readable after another restart
+            vec.push(42).unwrap();
+            drop(vec);
+            let vec = PersistentVector::open(dir.path()).unwrap();
+            assert_eq!(vec.as_slice().last(), Some(&42), "log truncated to {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn test_short_write_is_cut_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.push(1).unwrap();
+
+        // The failed push is not applied, and the later pushes are not hidden behind its torn record
+        FaultyLog::install(&mut vec, dir.path(), Some(2), false);
+        assert!(vec.push(300_000).is_err());
+        assert_eq!(vec.as_slice(), &[1]);
+        vec.push(3).unwrap();
+        vec.push(4).unwrap();
+        drop(vec);
+
+        let vec = PersistentVector::open(dir.path()).unwrap();
+        assert_eq!(vec.as_slice(), &[1, 3, 4]);
+        assert_eq!(vec.log_records(), 3);
+    }
+
+    #[test]
+    fn test_torn_record_that_cannot_be_cut_off_poisons() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.set_compaction_threshold(0);
+        vec.push(1).unwrap();
+
+        FaultyLog::install(&mut vec, dir.path(), Some(1), true);
+        assert!(vec.push(2).is_err());
+
+        // Nothing may be appended after the torn record until a compaction replaces the log
+        assert!(vec.push(3).is_err());
+        assert!(vec.delete(0).is_err());
+        assert_eq!(vec.as_slice(), &[1]);
+        vec.compact().unwrap();
+        vec.push(5).unwrap();
+        drop(vec);
+
+        assert_eq!(PersistentVector::open(dir.path()).unwrap().as_slice(), &[1, 5]);
+    }
+
+    #[test]
+    fn test_record_that_fails_to_apply_is_cut_off() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.set_compaction_threshold(0);
+        vec.push(1).unwrap();
+
+        // Records the public methods would reject are logged first and then fail to apply
+        assert!(vec.commit(Record::Delete(5)).is_err());
+        assert!(vec.commit(Record::Insert(7, 2)).is_err());
+        assert_eq!(vec.as_slice(), &[1]);
+        assert_eq!(vec.log_records(), 1);
+        vec.push(3).unwrap();
+        drop(vec);
+
+        let vec = PersistentVector::open(dir.path()).unwrap();
+        assert_eq!(vec.as_slice(), &[1, 3]);
+        assert_eq!(vec.log_records(), 2);
+    }
+
+    #[test]
+    fn test_failed_auto_compaction_does_not_fail_the_mutation() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.set_compaction_threshold(2);
+
+        // A directory in the way of the temporary snapshot makes every compaction fail
+        let blocker = dir.path().join(SNAPSHOT_TEMP_FILE);
+        fs::create_dir(&blocker).unwrap();
+        vec.push(1).unwrap();
+        vec.push(2).unwrap();
+        vec.push(3).unwrap();
+        assert_eq!(vec.log_records(), 3);
+
+        fs::remove_dir(&blocker).unwrap();
+        vec.push(4).unwrap();
+        assert_eq!(vec.log_records(), 0);
+        drop(vec);
+        assert_eq!(PersistentVector::open(dir.path()).unwrap().as_slice(), &[1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_log_restart_failing_after_compaction() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        vec.set_compaction_threshold(0);
+        vec.push(1).unwrap();
+
+        // With a directory where the log was, the snapshot is replaced but the log cannot be restarted
+        let log_path = dir.path().join(LOG_FILE);
+        fs::remove_file(&log_path).unwrap();
+        fs::create_dir(&log_path).unwrap();
+        assert!(vec.compact().is_err());
+
+        // Mutations fail without being applied until the log can be restarted for the new snapshot
+        assert!(vec.push(2).is_err());
+        assert_eq!(vec.as_slice(), &[1]);
+        fs::remove_dir(&log_path).unwrap();
+        vec.push(2).unwrap();
+        drop(vec);
+
+        let vec = PersistentVector::open(dir.path()).unwrap();
+        assert_eq!(vec.as_slice(), &[1, 2]);
+        assert_eq!(vec.log_records(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Index out of bounds")]
+    fn test_invalid_index_is_not_logged() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut vec = PersistentVector::open(dir.path()).unwrap();
+        let _ = vec.delete(0);
+    }
+}
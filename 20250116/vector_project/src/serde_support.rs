@@ -0,0 +1,97 @@
+// Serde support for `Vector`, enabled by the `serde` feature:
+// * Serialize - writes the elements as a sequence, like a slice
+// * Deserialize - reads a sequence, reserving capacity from its length hint once;
+//     malformed input and allocation failures become deserialization errors instead of panics
+
+use crate::allocator::Allocator;
+use crate::growth::GrowthPolicy;
+use crate::vector::Vector;
+use serde::de::{Error, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+
+// Upper bound, in bytes, on what a length hint may reserve up front, so that a hostile hint cannot
+// trigger a huge allocation before any element has been read
+const MAX_PREALLOCATION: usize = 1024 * 1024;
+
+impl<T: Serialize, P: GrowthPolicy, A: Allocator> Serialize for Vector<T, P, A> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+impl<'de, T: Deserialize<'de>, P: GrowthPolicy + Default> Deserialize<'de> for Vector<T, P> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(VectorVisitor(PhantomData))
+    }
+}
+
+// Builds a `Vector` from a serialized sequence
+struct VectorVisitor<T, P>(PhantomData<(T, P)>);
+
+impl<'de, T: Deserialize<'de>, P: GrowthPolicy + Default> Visitor<'de> for VectorVisitor<T, P> {
+    type Value = Vector<T, P>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let hint = seq.size_hint().unwrap_or(0);
+        let limit = MAX_PREALLOCATION / mem::size_of::<T>().max(1);
+
+        let mut vec = Vector::try_with_policy(0, P::default()).map_err(A::Error::custom)?;
+        vec.try_reserve(hint.min(limit)).map_err(A::Error::custom)?;
+        while let Some(item) = seq.next_element()? {
+            vec.try_push(item).map_err(A::Error::custom)?;
+        }
+        Ok(vec)
+    }
+}
+
+// Unit tests for the serde support
+#[cfg(test)]
+mod tests {
+    use crate::growth::NeverShrink;
+    use crate::vector::Vector;
+    use crate::vector;
+
+    #[test]
+    fn test_round_trip() {
+        let vec = vector![1, -2, 3];
+        let json = serde_json::to_string(&vec).unwrap();
+        assert_eq!(json, "[1,-2,3]");
+        let back: Vector<i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, vec);
+
+        // Non-`Copy` elements and custom policies
+        let vec: Vector<String, NeverShrink> = serde_json::from_str(r#"["a","b"]"#).unwrap();
+        assert_eq!(vec, ["a", "b"]);
+        assert_eq!(serde_json::to_string(&vec).unwrap(), r#"["a","b"]"#);
+
+        let empty: Vector<u64> = serde_json::from_str("[]").unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_rejects_malformed_input() {
+        assert!(serde_json::from_str::<Vector<i32>>("[1, 2").is_err());
+        assert!(serde_json::from_str::<Vector<i32>>("[1, \"two\"]").is_err());
+        assert!(serde_json::from_str::<Vector<i32>>("{\"a\": 1}").is_err());
+        assert!(serde_json::from_str::<Vector<u8>>("[256]").is_err());
+    }
+
+    #[test]
+    fn test_capacity_from_length_hint() {
+        use serde::de::value::{Error, SeqDeserializer};
+        use serde::Deserialize;
+
+        // A sequence that knows its length reserves the final capacity once
+        let deserializer = SeqDeserializer::<_, Error>::new(0..100);
+        let vec = Vector::<i32>::deserialize(deserializer).unwrap();
+        assert_eq!(vec.size(), 100);
+        assert_eq!(vec.capacity(), 128);
+    }
+}
@@ -0,0 +1,405 @@
+// Vectorized scans over `i32` slices, used by `Vector<i32>`:
+// * find(items, item) - index of the first occurrence
+// * count(items, item) - number of occurrences
+// * retain_ne(items, item) - moves every element not equal to `item` to the front, returns how many
+// * min(items), max(items), sum(items)
+//
+// On x86_64 each function picks the widest instruction set the CPU supports at runtime: AVX2
+// (8 lanes), otherwise SSE2 (4 lanes, always available on x86_64). Other targets use the scalar
+// loops in `scalar`, which define the results: every vectorized path returns exactly what its
+// scalar counterpart does, including `sum`, which adds with 64-bit wrapping arithmetic.
+
+// Instruction sets the functions can dispatch to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Scalar,
+    #[allow(dead_code)] // Only selected on x86_64
+    Sse2,
+    #[allow(dead_code)] // Only selected on x86_64
+    Avx2,
+}
+
+// Detects the widest supported instruction set; the standard library caches the CPU query
+fn level() -> Level {
+    #[cfg(target_arch = "x86_64")]
+    {
+        if is_x86_feature_detected!("avx2") {
+            return Level::Avx2;
+        }
+        return Level::Sse2;
+    }
+    #[allow(unreachable_code)]
+    Level::Scalar
+}
+
+// Returns the index of the first element equal to `item`
+pub fn find(items: &[i32], item: i32) -> Option<usize> {
+    match level() {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::find_avx2(items, item) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 => unsafe { x86::find_sse2(items, item) },
+        _ => scalar::find(items, item),
+    }
+}
+
+// Returns the number of elements equal to `item`
+pub fn count(items: &[i32], item: i32) -> usize {
+    match level() {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::count_avx2(items, item) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 => unsafe { x86::count_sse2(items, item) },
+        _ => scalar::count(items, item),
+    }
+}
+
+// Returns the smallest element, or `None` if there are none
+pub fn min(items: &[i32]) -> Option<i32> {
+    match level() {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::min_avx2(items) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 => unsafe { x86::min_sse2(items) },
+        _ => scalar::min(items),
+    }
+}
+
+// Returns the largest element, or `None` if there are none
+pub fn max(items: &[i32]) -> Option<i32> {
+    match level() {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::max_avx2(items) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 => unsafe { x86::max_sse2(items) },
+        _ => scalar::max(items),
+    }
+}
+
+// Returns the sum of all elements as an `i64`, wrapping on overflow
+pub fn sum(items: &[i32]) -> i64 {
+    match level() {
+        #[cfg(target_arch = "x86_64")]
+        Level::Avx2 => unsafe { x86::sum_avx2(items) },
+        #[cfg(target_arch = "x86_64")]
+        Level::Sse2 => unsafe { x86::sum_sse2(items) },
+        _ => scalar::sum(items),
+    }
+}
+
+// Moves every element not equal to `item` to the front, keeping their order, and returns how
+// many there are; `find` locates each run of elements to remove and each kept run is moved at once
+pub fn retain_ne(items: &mut [i32], item: i32) -> usize {
+    let len = items.len();
+    let Some(mut write) = find(items, item) else {
+        return len;
+    };
+    let mut read = write + 1;
+    while read < len {
+        if items[read] == item {
+            read += 1;
+            continue;
+        }
+        let run_end = find(&items[read..], item).map_or(len, |offset| read + offset);
+        items.copy_within(read..run_end, write);
+        write += run_end - read;
+        read = run_end;
+    }
+    write
+}
+
+// Reference implementations, used on targets without vector support and to finish the tails
+// that do not fill a whole vector register
+mod scalar {
+    pub fn find(items: &[i32], item: i32) -> Option<usize> {
+        items.iter().position(|&x| x == item)
+    }
+
+    pub fn count(items: &[i32], item: i32) -> usize {
+        items.iter().filter(|&&x| x == item).count()
+    }
+
+    pub fn min(items: &[i32]) -> Option<i32> {
+        items.iter().copied().min()
+    }
+
+    pub fn max(items: &[i32]) -> Option<i32> {
+        items.iter().copied().max()
+    }
+
+    pub fn sum(items: &[i32]) -> i64 {
+        items.iter().fold(0i64, |acc, &x| acc.wrapping_add(x as i64))
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use super::scalar;
+    use std::arch::x86_64::*;
+
+    // Number of vectors counted before the per-lane counters are added up, so they cannot overflow
+    const COUNT_BLOCK: usize = 1 << 24;
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn find_sse2(items: &[i32], item: i32) -> Option<usize> {
+        let needle = _mm_set1_epi32(item);
+        let chunks = items.chunks_exact(4);
+        let tail = chunks.remainder();
+        for (i, chunk) in chunks.enumerate() {
+            let values = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            let mask = _mm_movemask_ps(_mm_castsi128_ps(_mm_cmpeq_epi32(values, needle)));
+            if mask != 0 {
+                return Some(i * 4 + mask.trailing_zeros() as usize);
+            }
+        }
+        scalar::find(tail, item).map(|offset| items.len() - tail.len() + offset)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn find_avx2(items: &[i32], item: i32) -> Option<usize> {
+        let needle = _mm256_set1_epi32(item);
+        let chunks = items.chunks_exact(8);
+        let tail = chunks.remainder();
+        for (i, chunk) in chunks.enumerate() {
+            let values = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+            let mask = _mm256_movemask_ps(_mm256_castsi256_ps(_mm256_cmpeq_epi32(values, needle)));
+            if mask != 0 {
+                return Some(i * 8 + mask.trailing_zeros() as usize);
+            }
+        }
+        scalar::find(tail, item).map(|offset| items.len() - tail.len() + offset)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn count_sse2(items: &[i32], item: i32) -> usize {
+        let needle = _mm_set1_epi32(item);
+        let mut total = 0;
+        for block in items.chunks(COUNT_BLOCK * 4) {
+            // A matching lane compares to -1, so subtracting the comparison counts matches
+            let mut counts = _mm_setzero_si128();
+            let chunks = block.chunks_exact(4);
+            let tail = chunks.remainder();
+            for chunk in chunks {
+                let values = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+                counts = _mm_sub_epi32(counts, _mm_cmpeq_epi32(values, needle));
+            }
+            let mut lanes = [0u32; 4];
+            _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, counts);
+            total += lanes.iter().map(|&lane| lane as usize).sum::<usize>() + scalar::count(tail, item);
+        }
+        total
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn count_avx2(items: &[i32], item: i32) -> usize {
+        let needle = _mm256_set1_epi32(item);
+        let mut total = 0;
+        for block in items.chunks(COUNT_BLOCK * 8) {
+            let mut counts = _mm256_setzero_si256();
+            let chunks = block.chunks_exact(8);
+            let tail = chunks.remainder();
+            for chunk in chunks {
+                let values = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+                counts = _mm256_sub_epi32(counts, _mm256_cmpeq_epi32(values, needle));
+            }
+            let mut lanes = [0u32; 8];
+            _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, counts);
+            total += lanes.iter().map(|&lane| lane as usize).sum::<usize>() + scalar::count(tail, item);
+        }
+        total
+    }
+
+    // SSE2 has no 32-bit min/max instruction, so pick lanes with a comparison mask
+    #[target_feature(enable = "sse2")]
+    unsafe fn select_sse2(mask: __m128i, if_set: __m128i, if_clear: __m128i) -> __m128i {
+        _mm_or_si128(_mm_and_si128(mask, if_set), _mm_andnot_si128(mask, if_clear))
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn min_sse2(items: &[i32]) -> Option<i32> {
+        let chunks = items.chunks_exact(4);
+        let tail = chunks.remainder();
+        let mut best = _mm_set1_epi32(i32::MAX);
+        for chunk in chunks {
+            let values = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            best = select_sse2(_mm_cmplt_epi32(values, best), values, best);
+        }
+        let mut lanes = [0i32; 4];
+        _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, best);
+        reduce(items, &lanes, tail, i32::min)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn max_sse2(items: &[i32]) -> Option<i32> {
+        let chunks = items.chunks_exact(4);
+        let tail = chunks.remainder();
+        let mut best = _mm_set1_epi32(i32::MIN);
+        for chunk in chunks {
+            let values = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            best = select_sse2(_mm_cmpgt_epi32(values, best), values, best);
+        }
+        let mut lanes = [0i32; 4];
+        _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, best);
+        reduce(items, &lanes, tail, i32::max)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn min_avx2(items: &[i32]) -> Option<i32> {
+        let chunks = items.chunks_exact(8);
+        let tail = chunks.remainder();
+        let mut best = _mm256_set1_epi32(i32::MAX);
+        for chunk in chunks {
+            best = _mm256_min_epi32(best, _mm256_loadu_si256(chunk.as_ptr() as *const __m256i));
+        }
+        let mut lanes = [0i32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, best);
+        reduce(items, &lanes, tail, i32::min)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn max_avx2(items: &[i32]) -> Option<i32> {
+        let chunks = items.chunks_exact(8);
+        let tail = chunks.remainder();
+        let mut best = _mm256_set1_epi32(i32::MIN);
+        for chunk in chunks {
+            best = _mm256_max_epi32(best, _mm256_loadu_si256(chunk.as_ptr() as *const __m256i));
+        }
+        let mut lanes = [0i32; 8];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, best);
+        reduce(items, &lanes, tail, i32::max)
+    }
+
+    // Combines the lanes of a min/max register with the tail; the lanes start out as the identity
+    // of `pick`, so they are only meaningful when there was at least one element
+    fn reduce(items: &[i32], lanes: &[i32], tail: &[i32], pick: fn(i32, i32) -> i32) -> Option<i32> {
+        if items.is_empty() {
+            return None;
+        }
+        lanes.iter().chain(tail).copied().reduce(pick)
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub unsafe fn sum_sse2(items: &[i32]) -> i64 {
+        let chunks = items.chunks_exact(4);
+        let tail = chunks.remainder();
+        let zero = _mm_setzero_si128();
+        let mut sums = _mm_setzero_si128();
+        for chunk in chunks {
+            // Sign-extend to 64 bits by interleaving each value with its sign mask
+            let values = _mm_loadu_si128(chunk.as_ptr() as *const __m128i);
+            let signs = _mm_cmpgt_epi32(zero, values);
+            sums = _mm_add_epi64(sums, _mm_unpacklo_epi32(values, signs));
+            sums = _mm_add_epi64(sums, _mm_unpackhi_epi32(values, signs));
+        }
+        let mut lanes = [0i64; 2];
+        _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, sums);
+        lanes[0].wrapping_add(lanes[1]).wrapping_add(scalar::sum(tail))
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn sum_avx2(items: &[i32]) -> i64 {
+        let chunks = items.chunks_exact(8);
+        let tail = chunks.remainder();
+        let mut sums = _mm256_setzero_si256();
+        for chunk in chunks {
+            let values = _mm256_loadu_si256(chunk.as_ptr() as *const __m256i);
+            sums = _mm256_add_epi64(sums, _mm256_cvtepi32_epi64(_mm256_castsi256_si128(values)));
+            sums = _mm256_add_epi64(sums, _mm256_cvtepi32_epi64(_mm256_extracti128_si256::<1>(values)));
+        }
+        let mut lanes = [0i64; 4];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, sums);
+        lanes.iter().fold(scalar::sum(tail), |acc, &lane| acc.wrapping_add(lane))
+    }
+}
+
+// Unit tests comparing every vectorized path with the scalar one
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Small xorshift generator, so the tests need no extra dependency and are reproducible
+    fn random_values(seed: u64, len: usize, range: i32) -> Vec<i32> {
+        let mut state = seed | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                if range == 0 {
+                    state as i32
+                } else {
+                    (state % (2 * range as u64)) as i32 - range
+                }
+            })
+            .collect()
+    }
+
+    // Inputs of every length around the register widths, with few and with many repeats
+    fn inputs() -> Vec<Vec<i32>> {
+        let mut inputs = vec![Vec::new(), vec![i32::MIN], vec![i32::MAX; 9], vec![i32::MIN, i32::MAX, 0, -1]];
+        for len in 0..70 {
+            inputs.push(random_values(len as u64, len, 4));
+            inputs.push(random_values(len as u64 + 1000, len, 0));
+        }
+        inputs.push(random_values(7, 100_000, 50));
+        inputs.push(random_values(8, 100_001, 0));
+        inputs
+    }
+
+    #[test]
+    fn test_matches_scalar() {
+        for items in inputs() {
+            for item in [-4, -1, 0, 3, i32::MIN, i32::MAX] {
+                assert_eq!(find(&items, item), scalar::find(&items, item));
+                assert_eq!(count(&items, item), scalar::count(&items, item));
+            }
+            assert_eq!(min(&items), scalar::min(&items));
+            assert_eq!(max(&items), scalar::max(&items));
+            assert_eq!(sum(&items), scalar::sum(&items));
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn test_every_instruction_set_matches_scalar() {
+        let avx2 = is_x86_feature_detected!("avx2");
+        for items in inputs() {
+            for item in [-4, 0, 3, i32::MIN] {
+                let expected = (scalar::find(&items, item), scalar::count(&items, item));
+                unsafe {
+                    assert_eq!((x86::find_sse2(&items, item), x86::count_sse2(&items, item)), expected);
+                    if avx2 {
+                        assert_eq!((x86::find_avx2(&items, item), x86::count_avx2(&items, item)), expected);
+                    }
+                }
+            }
+            let expected = (scalar::min(&items), scalar::max(&items), scalar::sum(&items));
+            unsafe {
+                assert_eq!((x86::min_sse2(&items), x86::max_sse2(&items), x86::sum_sse2(&items)), expected);
+                if avx2 {
+                    assert_eq!((x86::min_avx2(&items), x86::max_avx2(&items), x86::sum_avx2(&items)), expected);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sum_does_not_overflow_i32() {
+        let items = vec![i32::MAX; 1000];
+        assert_eq!(sum(&items), i32::MAX as i64 * 1000);
+        let items = vec![i32::MIN; 1001];
+        assert_eq!(sum(&items), i32::MIN as i64 * 1001);
+    }
+
+    #[test]
+    fn test_retain_ne() {
+        for items in inputs() {
+            for item in [-4, 0, 3] {
+                let expected: Vec<i32> = items.iter().copied().filter(|&x| x != item).collect();
+                let mut actual = items.clone();
+                let len = retain_ne(&mut actual, item);
+                assert_eq!(&actual[..len], &expected[..]);
+            }
+        }
+    }
+}
@@ -0,0 +1,531 @@
+// In-place sorting algorithms for `Vector`:
+// * introsort(vec) - the default unstable sort: quicksort with a median-of-three pivot, finishing
+//     small ranges with insertion sort and switching to heapsort when recursion gets too deep
+// * merge_sort(vec) - stable sort, merging through a scratch buffer of half the length
+// * heapsort(vec) - unstable, O(n log n) in the worst case and no extra memory
+// * radix_sort(vec) - stable LSD radix sort for `Vector<i32>`, one pass per key byte
+// * the `_by(compare)` and `_by_key(key)` variants sort by a comparator or by a key; radix sort
+//     takes an `i32` key for any `Copy` element
+//
+// Every sort leaves the vector holding each of its elements exactly once even if a comparator or
+// key function panics; only the order is unspecified then. The sorts work on a vector with any
+// allocator, but merge and radix sort take their scratch buffers from the global one.
+
+use crate::allocator::Allocator;
+use crate::growth::GrowthPolicy;
+use crate::vector::{deallocate, try_allocate, Vector};
+use std::cmp::Ordering;
+use std::mem;
+use std::ptr;
+
+// Ranges this short are finished with insertion sort
+const INSERTION_THRESHOLD: usize = 20;
+
+// Sorts a vector in ascending order without preserving the order of equal elements
+pub fn introsort<T: Ord, P: GrowthPolicy, A: Allocator>(vec: &mut Vector<T, P, A>) {
+    introsort_by(vec, T::cmp);
+}
+
+// Sorts a vector by a comparator without preserving the order of equal elements
+pub fn introsort_by<T, P, A, F>(vec: &mut Vector<T, P, A>, mut compare: F)
+where
+    P: GrowthPolicy,
+    A: Allocator,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let items = vec.as_mut_slice();
+    // After 2 * log2(n) levels quicksort is not splitting well, so heapsort takes over
+    let limit = 2 * (usize::BITS - items.len().leading_zeros());
+    introsort_slice(items, limit, &mut |a, b| compare(a, b) == Ordering::Less);
+}
+
+// Sorts a vector by a key without preserving the order of elements with equal keys
+pub fn introsort_by_key<T, P, A, K, F>(vec: &mut Vector<T, P, A>, mut key: F)
+where
+    P: GrowthPolicy,
+    A: Allocator,
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    introsort_by(vec, |a, b| key(a).cmp(&key(b)));
+}
+
+// Sorts a vector in ascending order, keeping equal elements in their original order
+pub fn merge_sort<T: Ord, P: GrowthPolicy, A: Allocator>(vec: &mut Vector<T, P, A>) {
+    merge_sort_by(vec, T::cmp);
+}
+
+// Sorts a vector by a comparator, keeping equal elements in their original order
+pub fn merge_sort_by<T, P, A, F>(vec: &mut Vector<T, P, A>, mut compare: F)
+where
+    P: GrowthPolicy,
+    A: Allocator,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    let items = vec.as_mut_slice();
+    if items.len() <= INSERTION_THRESHOLD {
+        insertion_sort(items, &mut |a, b| compare(a, b) == Ordering::Less);
+        return;
+    }
+    // A merge only ever moves its left half out, which is at most half the length
+    let scratch = Scratch::new(items.len() / 2);
+    merge_sort_slice(items, scratch.data, &mut |a, b| compare(a, b) == Ordering::Less);
+}
+
+// Sorts a vector by a key, keeping elements with equal keys in their original order
+pub fn merge_sort_by_key<T, P, A, K, F>(vec: &mut Vector<T, P, A>, mut key: F)
+where
+    P: GrowthPolicy,
+    A: Allocator,
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    merge_sort_by(vec, |a, b| key(a).cmp(&key(b)));
+}
+
+// Sorts a vector in ascending order with heapsort
+pub fn heapsort<T: Ord, P: GrowthPolicy, A: Allocator>(vec: &mut Vector<T, P, A>) {
+    heapsort_by(vec, T::cmp);
+}
+
+// Sorts a vector by a comparator with heapsort
+pub fn heapsort_by<T, P, A, F>(vec: &mut Vector<T, P, A>, mut compare: F)
+where
+    P: GrowthPolicy,
+    A: Allocator,
+    F: FnMut(&T, &T) -> Ordering,
+{
+    heapsort_slice(vec.as_mut_slice(), &mut |a, b| compare(a, b) == Ordering::Less);
+}
+
+// Sorts a vector by a key with heapsort
+pub fn heapsort_by_key<T, P, A, K, F>(vec: &mut Vector<T, P, A>, mut key: F)
+where
+    P: GrowthPolicy,
+    A: Allocator,
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    heapsort_by(vec, |a, b| key(a).cmp(&key(b)));
+}
+
+// Sorts an `i32` vector in ascending order with radix sort
+pub fn radix_sort<P: GrowthPolicy, A: Allocator>(vec: &mut Vector<i32, P, A>) {
+    radix_sort_by_key(vec, |&item| item);
+}
+
+// Sorts a vector by an `i32` key with radix sort, keeping elements with equal keys in their
+// original order. Each key is computed once.
+pub fn radix_sort_by_key<T, P, A, F>(vec: &mut Vector<T, P, A>, mut key: F)
+where
+    T: Copy,
+    P: GrowthPolicy,
+    A: Allocator,
+    F: FnMut(&T) -> i32,
+{
+    let items = vec.as_mut_slice();
+    let len = items.len();
+    if len < 2 {
+        return;
+    }
+
+    // Flipping the sign bit makes the unsigned order of the keys match their signed order
+    let keys = Scratch::<u32>::new(len);
+    for (i, item) in items.iter().enumerate() {
+        unsafe { keys.data.add(i).write(key(item) as u32 ^ 0x8000_0000) };
+    }
+
+    // Each pass scatters the elements and their keys from one pair of buffers into the other by
+    // one byte of the key, least significant first
+    let other_keys = Scratch::<u32>::new(len);
+    let other_items = Scratch::<T>::new(len);
+    let data = items.as_mut_ptr();
+    let (mut src_items, mut src_keys) = (data, keys.data);
+    let (mut dst_items, mut dst_keys) = (other_items.data, other_keys.data);
+    for shift in (0..u32::BITS).step_by(8) {
+        let byte = |key: u32| (key >> shift & 0xff) as usize;
+        let mut counts = [0usize; 256];
+        for i in 0..len {
+            counts[byte(unsafe { *src_keys.add(i) })] += 1;
+        }
+        // A byte shared by every key would leave the order unchanged
+        if counts.contains(&len) {
+            continue;
+        }
+
+        let mut offsets = [0usize; 256];
+        for b in 1..256 {
+            offsets[b] = offsets[b - 1] + counts[b - 1];
+        }
+        for i in 0..len {
+            unsafe {
+                let key = *src_keys.add(i);
+                let slot = &mut offsets[byte(key)];
+                dst_items.add(*slot).write(*src_items.add(i));
+                dst_keys.add(*slot).write(key);
+                *slot += 1;
+            }
+        }
+        mem::swap(&mut src_items, &mut dst_items);
+        mem::swap(&mut src_keys, &mut dst_keys);
+    }
+    if src_items != data {
+        unsafe { ptr::copy_nonoverlapping(src_items, data, len) };
+    }
+}
+
+// An uninitialized buffer allocated like a `Vector` buffer; it never drops its contents
+struct Scratch<T> {
+    data: *mut T,
+    capacity: usize,
+}
+
+impl<T> Scratch<T> {
+    fn new(capacity: usize) -> Self {
+        let data = try_allocate(capacity).unwrap_or_else(|err| err.raise());
+        Scratch { data, capacity }
+    }
+}
+
+impl<T> Drop for Scratch<T> {
+    fn drop(&mut self) {
+        unsafe { deallocate(self.data, self.capacity) };
+    }
+}
+
+// Sorts short slices by moving each element left past the larger ones before it; stable
+fn insertion_sort<T, F: FnMut(&T, &T) -> bool>(items: &mut [T], is_less: &mut F) {
+    for i in 1..items.len() {
+        let mut j = i;
+        while j > 0 && is_less(&items[j], &items[j - 1]) {
+            items.swap(j, j - 1);
+            j -= 1;
+        }
+    }
+}
+
+// Quicksort that recurses into the smaller side and loops on the larger one, so the stack stays
+// O(log n) deep; each level spends one unit of `limit`
+fn introsort_slice<T, F: FnMut(&T, &T) -> bool>(mut items: &mut [T], mut limit: u32, is_less: &mut F) {
+    loop {
+        if items.len() <= INSERTION_THRESHOLD {
+            insertion_sort(items, is_less);
+            return;
+        }
+        if limit == 0 {
+            heapsort_slice(items, is_less);
+            return;
+        }
+        limit -= 1;
+
+        let pivot = median_of_three(items, is_less);
+        items.swap(0, pivot);
+        let pivot = partition(items, is_less);
+        let (left, right) = items.split_at_mut(pivot);
+        let right = &mut right[1..];
+        if left.len() < right.len() {
+            introsort_slice(left, limit, is_less);
+            items = right;
+        } else {
+            introsort_slice(right, limit, is_less);
+            items = left;
+        }
+    }
+}
+
+// Returns the index of the median of the first, middle and last elements
+fn median_of_three<T, F: FnMut(&T, &T) -> bool>(items: &[T], is_less: &mut F) -> usize {
+    let (a, b, c) = (0, items.len() / 2, items.len() - 1);
+    let (a, b) = if is_less(&items[b], &items[a]) { (b, a) } else { (a, b) };
+    if is_less(&items[c], &items[a]) {
+        a
+    } else if is_less(&items[c], &items[b]) {
+        c
+    } else {
+        b
+    }
+}
+
+// Partitions around the pivot at index 0 and returns where the pivot ends up: nothing before it is
+// greater and nothing after it is less. Elements equal to the pivot stop both scans and are swapped,
+// so runs of equal elements are split evenly instead of all landing on one side.
+fn partition<T, F: FnMut(&T, &T) -> bool>(items: &mut [T], is_less: &mut F) -> usize {
+    let (mut l, mut r) = (1, items.len() - 1);
+    loop {
+        while l <= r && is_less(&items[l], &items[0]) {
+            l += 1;
+        }
+        while l <= r && is_less(&items[0], &items[r]) {
+            r -= 1;
+        }
+        if l >= r {
+            break;
+        }
+        items.swap(l, r);
+        l += 1;
+        r -= 1;
+    }
+    items.swap(0, r);
+    r
+}
+
+// Builds a max-heap in O(n), then repeatedly moves its root behind the heap
+fn heapsort_slice<T, F: FnMut(&T, &T) -> bool>(items: &mut [T], is_less: &mut F) {
+    let len = items.len();
+    for root in (0..len / 2).rev() {
+        sift_down(items, root, len, is_less);
+    }
+    for end in (1..len).rev() {
+        items.swap(0, end);
+        sift_down(items, 0, end, is_less);
+    }
+}
+
+// Moves the element at `root` down the heap formed by the first `end` elements
+fn sift_down<T, F: FnMut(&T, &T) -> bool>(items: &mut [T], mut root: usize, end: usize, is_less: &mut F) {
+    loop {
+        let mut child = 2 * root + 1;
+        if child >= end {
+            return;
+        }
+        if child + 1 < end && is_less(&items[child], &items[child + 1]) {
+            child += 1;
+        }
+        if !is_less(&items[root], &items[child]) {
+            return;
+        }
+        items.swap(root, child);
+        root = child;
+    }
+}
+
+// Sorts both halves, then merges them unless they are already in order. `scratch` has room for
+// at least half of `items`.
+fn merge_sort_slice<T, F: FnMut(&T, &T) -> bool>(items: &mut [T], scratch: *mut T, is_less: &mut F) {
+    let len = items.len();
+    if len <= INSERTION_THRESHOLD {
+        insertion_sort(items, is_less);
+        return;
+    }
+    let mid = len / 2;
+    merge_sort_slice(&mut items[..mid], scratch, is_less);
+    merge_sort_slice(&mut items[mid..], scratch, is_less);
+    if is_less(&items[mid], &items[mid - 1]) {
+        unsafe { merge(items, mid, scratch, is_less) };
+    }
+}
+
+// Merges the sorted runs `items[..mid]` and `items[mid..]`. The left run is moved into `scratch`
+// and merged back from the front; on ties the left element goes first, which keeps the sort stable.
+unsafe fn merge<T, F: FnMut(&T, &T) -> bool>(items: &mut [T], mid: usize, scratch: *mut T, is_less: &mut F) {
+    let len = items.len();
+    let data = items.as_mut_ptr();
+    ptr::copy_nonoverlapping(data, scratch, mid);
+
+    // Everything not yet merged back is either in `scratch[start..end]` or after `right`, and the
+    // gap before `right` is exactly as long as what is left in `scratch`
+    let mut hole = MergeHole { start: scratch, end: scratch.add(mid), dest: data };
+    let mut right = data.add(mid);
+    let right_end = data.add(len);
+    while hole.start < hole.end && right < right_end {
+        let take_right = is_less(&*right, &*hole.start);
+        let src = if take_right { right } else { hole.start };
+        ptr::copy_nonoverlapping(src, hole.dest, 1);
+        hole.dest = hole.dest.add(1);
+        if take_right {
+            right = right.add(1);
+        } else {
+            hole.start = hole.start.add(1);
+        }
+    }
+    // Dropping `hole` moves the rest of the left run into the gap
+}
+
+// The part of the left run still in the scratch buffer and where it belongs; it is moved back when
+// the merge finishes or when a comparison panics
+struct MergeHole<T> {
+    start: *mut T,
+    end: *mut T,
+    dest: *mut T,
+}
+
+impl<T> Drop for MergeHole<T> {
+    fn drop(&mut self) {
+        unsafe {
+            let remaining = self.end.offset_from(self.start) as usize;
+            ptr::copy_nonoverlapping(self.start, self.dest, remaining);
+        }
+    }
+}
+
+// Unit tests comparing every sort with the standard library's
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocator::BumpAllocator;
+    use crate::growth::Doubling;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    // Small xorshift generator, so the tests need no extra dependency and are reproducible
+    fn random_values(seed: u64, len: usize, range: u64) -> Vec<i32> {
+        let mut state = seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                if range == 0 {
+                    state as i32
+                } else {
+                    (state % range) as i32
+                }
+            })
+            .collect()
+    }
+
+    // Random inputs of many lengths, with few or many distinct values, plus the classic bad cases
+    // for quicksort: sorted, reversed, all equal and organ-pipe
+    fn inputs() -> Vec<Vec<i32>> {
+        let mut inputs = Vec::new();
+        for (seed, len) in [0, 1, 2, 3, 15, 20, 21, 64, 100, 1000, 10_000].into_iter().enumerate() {
+            inputs.push(random_values(seed as u64, len, 0));
+            inputs.push(random_values(seed as u64, len, 5));
+        }
+        inputs.push((0..5000).collect());
+        inputs.push((0..5000).rev().collect());
+        inputs.push(vec![7; 5000]);
+        inputs.push((0..2500).chain((0..2500).rev()).collect());
+        inputs.push(vec![i32::MIN, i32::MAX, 0, -1, 1, i32::MIN, i32::MAX]);
+        inputs
+    }
+
+    #[test]
+    fn test_sorts_match_std() {
+        type IntSort = fn(&mut Vector<i32>);
+        let sorts: [(&str, IntSort); 4] = [
+            ("introsort", introsort),
+            ("merge_sort", merge_sort),
+            ("heapsort", heapsort),
+            ("radix_sort", radix_sort),
+        ];
+        for input in inputs() {
+            let mut expected = input.clone();
+            expected.sort();
+            for (name, sort) in sorts {
+                let mut vec = Vector::from(input.clone());
+                sort(&mut vec);
+                assert_eq!(vec, expected, "{} on {} items", name, input.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sorts_vectors_from_other_allocators() {
+        type BumpSort = fn(&mut Vector<i32, Doubling, BumpAllocator>);
+        let sorts: [(&str, BumpSort); 4] = [
+            ("introsort", introsort),
+            ("merge_sort", merge_sort),
+            ("heapsort", heapsort),
+            ("radix_sort", radix_sort),
+        ];
+        let input = random_values(7, 1000, 0);
+        let mut expected = input.clone();
+        expected.sort();
+        for (name, sort) in sorts {
+            let mut vec = Vector::new_in(0, BumpAllocator::default());
+            vec.extend_from_slice(&input);
+            sort(&mut vec);
+            assert_eq!(vec, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_comparators_and_keys() {
+        for input in inputs() {
+            let mut expected = input.clone();
+            expected.sort_by(|a, b| b.cmp(a));
+
+            let mut vec = Vector::from(input.clone());
+            introsort_by(&mut vec, |a, b| b.cmp(a));
+            assert_eq!(vec, expected);
+            let mut vec = Vector::from(input.clone());
+            merge_sort_by(&mut vec, |a, b| b.cmp(a));
+            assert_eq!(vec, expected);
+            let mut vec = Vector::from(input.clone());
+            heapsort_by_key(&mut vec, |&x| std::cmp::Reverse(x));
+            assert_eq!(vec, expected);
+            let mut vec = Vector::from(input.clone());
+            radix_sort_by_key(&mut vec, |&x| !x);
+            assert_eq!(vec, expected);
+        }
+    }
+
+    #[test]
+    fn test_stable_sorts_keep_equal_keys_in_order() {
+        for input in inputs() {
+            // Pair every value with its position; sorting by value alone must keep positions ascending
+            let pairs: Vec<(i32, usize)> = input.iter().map(|&x| x % 7).zip(0..).collect();
+            let mut expected = pairs.clone();
+            expected.sort_by_key(|&(key, _)| key);
+
+            let mut vec = Vector::from(pairs.clone());
+            merge_sort_by_key(&mut vec, |&(key, _)| key);
+            assert_eq!(vec, expected);
+            let mut vec = Vector::from(pairs.clone());
+            radix_sort_by_key(&mut vec, |&(key, _)| key);
+            assert_eq!(vec, expected);
+
+            // The unstable sorts only have to agree on the keys
+            let mut vec = Vector::from(pairs.clone());
+            introsort_by_key(&mut vec, |&(key, _)| key);
+            assert!(vec.iter().map(|p| p.0).eq(expected.iter().map(|p| p.0)));
+        }
+    }
+
+    #[test]
+    fn test_non_copy_elements() {
+        let input: Vec<String> = random_values(42, 500, 100).iter().map(|x| x.to_string()).collect();
+        let mut expected = input.clone();
+        expected.sort();
+        let sorts: [fn(&mut Vector<String>); 3] = [introsort, merge_sort, heapsort];
+        for sort in sorts {
+            let mut vec = Vector::from(input.clone());
+            sort(&mut vec);
+            assert_eq!(vec, expected);
+        }
+    }
+
+    #[test]
+    fn test_panicking_comparator_keeps_every_element() {
+        let tracker = Rc::new(());
+        let input: Vec<(i32, Rc<()>)> = random_values(9, 300, 0).into_iter().map(|x| (x, Rc::clone(&tracker))).collect();
+        type Sort = fn(&mut Vector<(i32, Rc<()>)>, &mut dyn FnMut(&(i32, Rc<()>), &(i32, Rc<()>)) -> Ordering);
+        let sorts: [Sort; 3] = [
+            |vec, compare| introsort_by(vec, compare),
+            |vec, compare| merge_sort_by(vec, compare),
+            |vec, compare| heapsort_by(vec, compare),
+        ];
+        for sort in sorts {
+            let mut vec = Vector::from(input.clone());
+            let mut calls = 0;
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                sort(&mut vec, &mut |a, b| {
+                    calls += 1;
+                    assert!(calls < 1000, "comparator gave up");
+                    a.0.cmp(&b.0)
+                })
+            }));
+            assert!(result.is_err());
+
+            // Still a permutation of the input, and dropping it releases every element once
+            let mut values: Vec<i32> = vec.iter().map(|p| p.0).collect();
+            let mut expected: Vec<i32> = input.iter().map(|p| p.0).collect();
+            values.sort();
+            expected.sort();
+            assert_eq!(values, expected);
+            drop(vec);
+            assert_eq!(Rc::strong_count(&tracker), input.len() + 1);
+        }
+    }
+}
@@ -0,0 +1,457 @@
+// Vector that keeps its elements in ascending order, created with `Vector::into_sorted` or `collect()`,
+// which sort stably:
+// * insert(item) - inserts after any equal elements and returns the index it landed at
+// * binary_search(item) - Ok(index) of an equal element, or Err(index) where it would be inserted
+// * lower_bound(item), upper_bound(item) - first index not less than / greater than item
+// * equal_range(item) - range of the elements equal to item
+// * contains(item), remove(item) - O(log n) search; remove takes out every equal element at once
+// * merge(other) - merges another sorted vector in linear time, keeping equal elements stable
+// * delete(index), pop(), into_inner() - taking elements out never breaks the order
+// * Deref to a slice - every read-only slice method, `len()`, indexing and iteration
+//
+// Elements are only reachable mutably through the methods above, so the order cannot be broken
+// from outside.
+
+use crate::allocator::{Allocator, Global};
+use crate::growth::{Doubling, GrowthPolicy};
+use crate::sort;
+use crate::vector::Vector;
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::ptr;
+
+// Define a `SortedVector` struct wrapping a `Vector` whose elements are in ascending order
+pub struct SortedVector<T, P = Doubling, A: Allocator = Global> {
+    inner: Vector<T, P, A>, // Sorted elements; equal elements keep the order they were inserted in
+}
+
+impl<T: Ord> SortedVector<T> {
+    // Creates an empty `SortedVector` with an initial capacity, like `Vector::new`
+    pub fn new(initial_capacity: usize) -> Self {
+        SortedVector { inner: Vector::new(initial_capacity) }
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy> SortedVector<T, P> {
+    // Creates an empty `SortedVector` growing and shrinking according to `policy`
+    pub fn with_policy(initial_capacity: usize, policy: P) -> Self {
+        SortedVector { inner: Vector::with_policy(initial_capacity, policy) }
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy, A: Allocator> SortedVector<T, P, A> {
+    // Creates an empty `SortedVector` like `with_policy`, taking its buffer from `alloc`
+    pub fn with_policy_in(initial_capacity: usize, policy: P, alloc: A) -> Self {
+        SortedVector { inner: Vector::with_policy_in(initial_capacity, policy, alloc) }
+    }
+
+    // Returns the elements as a slice
+    pub fn as_slice(&self) -> &[T] {
+        self.inner.as_slice()
+    }
+
+    // Returns the number of elements
+    pub fn size(&self) -> usize {
+        self.inner.size()
+    }
+
+    // Returns the number of elements the buffer can hold without resizing
+    pub fn capacity(&self) -> usize {
+        self.inner.capacity()
+    }
+
+    // Checks if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    // Inserts an item after every element equal to it and returns its index
+    pub fn insert(&mut self, item: T) -> usize {
+        let index = self.upper_bound(&item);
+        if index == self.size() {
+            self.inner.push(item);
+        } else {
+            self.inner.insert(index, item);
+        }
+        index
+    }
+
+    // Searches for an item; returns `Ok` with the index of the first equal element, or `Err` with
+    // the index where the item would have to be inserted
+    pub fn binary_search(&self, item: &T) -> Result<usize, usize> {
+        let index = self.lower_bound(item);
+        match self.as_slice().get(index) {
+            Some(found) if found == item => Ok(index),
+            _ => Err(index),
+        }
+    }
+
+    // Returns the index of the first element that is not less than `item`
+    pub fn lower_bound(&self, item: &T) -> usize {
+        self.partition_point(|x| x < item)
+    }
+
+    // Returns the index of the first element that is greater than `item`
+    pub fn upper_bound(&self, item: &T) -> usize {
+        self.partition_point(|x| x <= item)
+    }
+
+    // Returns the range of indices holding elements equal to `item`, empty if there are none
+    pub fn equal_range(&self, item: &T) -> Range<usize> {
+        self.lower_bound(item)..self.upper_bound(item)
+    }
+
+    // Checks if an element equal to `item` exists
+    pub fn contains(&self, item: &T) -> bool {
+        self.binary_search(item).is_ok()
+    }
+
+    // Removes every element equal to `item` with a single shift and returns how many there were
+    pub fn remove(&mut self, item: &T) -> usize {
+        let range = self.equal_range(item);
+        let count = range.len();
+        if count > 0 {
+            self.inner.drain(range);
+        }
+        count
+    }
+
+    // Deletes the element at an index and returns it, blows up if the index is out of bounds
+    pub fn delete(&mut self, index: usize) -> T {
+        self.inner.try_delete(index).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    // Removes the largest element and returns it, `None` if empty
+    pub fn pop(&mut self) -> Option<T> {
+        self.inner.pop()
+    }
+
+    // Merges the elements of another sorted vector in linear time. Elements are moved, never
+    // cloned; of equal elements, those already here come first. The buffer grows at most once.
+    pub fn merge<Q: GrowthPolicy, B: Allocator>(&mut self, other: SortedVector<T, Q, B>) {
+        let mut right = other.inner;
+        let (left_len, right_len) = (self.size(), right.size());
+        self.inner.reserve(right_len);
+
+        // Both vectors give up their elements while they are moved; the hole puts them back
+        // together in `self` even if a comparison panics, so `right` only frees its buffer
+        unsafe {
+            let left = self.inner.raw_data();
+            let right_data = right.raw_data();
+            right.set_size(0);
+            self.inner.set_size(0);
+            let total = left_len + right_len;
+            let mut hole = MergeHole { vec: &mut self.inner, left, left_len, right: right_data, right_len, total };
+
+            // Fill from the back, taking the larger element; on ties the right one goes last
+            while hole.left_len > 0 && hole.right_len > 0 {
+                let l = hole.left.add(hole.left_len - 1);
+                let r = hole.right.add(hole.right_len - 1);
+                let dest = hole.left.add(hole.left_len + hole.right_len - 1);
+                if *r < *l {
+                    ptr::copy_nonoverlapping(l, dest, 1);
+                    hole.left_len -= 1;
+                } else {
+                    ptr::copy_nonoverlapping(r, dest, 1);
+                    hole.right_len -= 1;
+                }
+            }
+        }
+    }
+
+    // Returns the underlying `Vector`, still sorted
+    pub fn into_inner(self) -> Vector<T, P, A> {
+        self.inner
+    }
+
+    // Returns the number of leading elements for which `pred` holds; `pred` must hold for a
+    // prefix of the elements and fail for the rest
+    fn partition_point<F: FnMut(&T) -> bool>(&self, mut pred: F) -> usize {
+        let items = self.as_slice();
+        let (mut low, mut high) = (0, items.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if pred(&items[mid]) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+// During `merge`, the merged elements sit at the end of the combined range, after a gap exactly
+// as long as the unmerged remainder of `right`. Dropping the hole moves that remainder into the
+// gap, after the unmerged `left` elements, and hands every element back to the vector.
+struct MergeHole<'a, T, P: GrowthPolicy, A: Allocator> {
+    vec: &'a mut Vector<T, P, A>,
+    left: *mut T,
+    left_len: usize,
+    right: *const T,
+    right_len: usize,
+    total: usize, // Number of elements in both vectors together
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> Drop for MergeHole<'_, T, P, A> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::copy_nonoverlapping(self.right, self.left.add(self.left_len), self.right_len);
+            self.vec.set_size(self.total);
+        }
+    }
+}
+
+// Sorts the elements once with a stable sort, so equal elements keep their order in `vec`
+impl<T: Ord, P: GrowthPolicy, A: Allocator> From<Vector<T, P, A>> for SortedVector<T, P, A> {
+    fn from(mut vec: Vector<T, P, A>) -> Self {
+        sort::merge_sort(&mut vec);
+        SortedVector { inner: vec }
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy + Default, A: Allocator + Default> FromIterator<T> for SortedVector<T, P, A> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        Self::from(iter.into_iter().collect::<Vector<T, P, A>>())
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy + Default, A: Allocator + Default> Default for SortedVector<T, P, A> {
+    fn default() -> Self {
+        SortedVector { inner: Vector::default() }
+    }
+}
+
+impl<T: Ord + Clone, P: GrowthPolicy + Clone, A: Allocator + Clone> Clone for SortedVector<T, P, A> {
+    fn clone(&self) -> Self {
+        SortedVector { inner: self.inner.clone() }
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy, A: Allocator> Deref for SortedVector<T, P, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T: Ord + fmt::Debug, P: GrowthPolicy, A: Allocator> fmt::Debug for SortedVector<T, P, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy, A: Allocator> PartialEq for SortedVector<T, P, A> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy, A: Allocator> Eq for SortedVector<T, P, A> {}
+
+impl<'a, T: Ord, P: GrowthPolicy, A: Allocator> IntoIterator for &'a SortedVector<T, P, A> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.as_slice().iter()
+    }
+}
+
+impl<T, P, A: Allocator> IntoIterator for SortedVector<T, P, A> {
+    type Item = T;
+    type IntoIter = crate::vector::IntoIter<T, A>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.inner.into_iter()
+    }
+}
+
+// Unit tests for the `SortedVector` struct
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::allocator::CountingAllocator;
+    use crate::vector;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    // Element compared by its number only, with a tag telling equal elements apart
+    #[derive(Debug)]
+    struct Tagged(i32, char);
+
+    impl PartialEq for Tagged {
+        fn eq(&self, other: &Self) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl Eq for Tagged {}
+
+    impl PartialOrd for Tagged {
+        fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+            Some(self.cmp(other))
+        }
+    }
+
+    impl Ord for Tagged {
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            self.0.cmp(&other.0)
+        }
+    }
+
+    #[test]
+    fn test_insert_keeps_order() {
+        let mut sorted = SortedVector::new(0);
+        for item in [5, 1, 4, 1, 5, 9, 2, 6, 5, 3] {
+            let index = sorted.insert(item);
+            assert_eq!(sorted[index], item);
+        }
+        assert_eq!(sorted.as_slice(), &[1, 1, 2, 3, 4, 5, 5, 5, 6, 9]);
+        assert_eq!(sorted.insert(0), 0);
+        assert_eq!(sorted.insert(10), 11);
+    }
+
+    #[test]
+    fn test_sorting_a_vector_is_stable() {
+        // Enough elements that the sort merges runs instead of only inserting
+        let items: Vector<Tagged> =
+            (0..300).map(|i| Tagged((i * 7919) % 5, char::from(b'a' + (i % 26) as u8))).collect();
+        let expected: Vec<(i32, char)> = {
+            let mut pairs: Vec<(i32, char)> = items.iter().map(|t| (t.0, t.1)).collect();
+            pairs.sort_by_key(|&(key, _)| key);
+            pairs
+        };
+        let sorted = items.into_sorted();
+        assert!(sorted.iter().map(|t| (t.0, t.1)).eq(expected.iter().copied()));
+    }
+
+    #[test]
+    fn test_searches() {
+        let sorted = vector![7, 1, 3, 3, 3, 5].into_sorted();
+        assert_eq!(sorted.as_slice(), &[1, 3, 3, 3, 5, 7]);
+        assert_eq!(sorted.binary_search(&3), Ok(1));
+        assert_eq!(sorted.binary_search(&4), Err(4));
+        assert_eq!(sorted.binary_search(&0), Err(0));
+        assert_eq!(sorted.binary_search(&8), Err(6));
+        assert_eq!((sorted.lower_bound(&3), sorted.upper_bound(&3)), (1, 4));
+        assert_eq!(sorted.equal_range(&3), 1..4);
+        assert_eq!(sorted.equal_range(&6), 5..5);
+        assert!(sorted.contains(&7));
+        assert!(!sorted.contains(&2));
+
+        let empty: SortedVector<i32> = SortedVector::new(0);
+        assert_eq!(empty.binary_search(&1), Err(0));
+        assert_eq!(empty.equal_range(&1), 0..0);
+    }
+
+    #[test]
+    fn test_matches_sorted_std_vec() {
+        // Every search agrees with a linear scan over a sorted `Vec`
+        let mut state = 12345u32;
+        let values: Vec<i32> = (0..500)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                (state >> 16) as i32 % 50
+            })
+            .collect();
+        let sorted: SortedVector<i32> = values.iter().copied().collect();
+        let mut expected = values.clone();
+        expected.sort();
+        assert_eq!(sorted.as_slice(), &expected[..]);
+        for item in -1..51 {
+            assert_eq!(sorted.lower_bound(&item), expected.iter().filter(|&&x| x < item).count());
+            assert_eq!(sorted.upper_bound(&item), expected.iter().filter(|&&x| x <= item).count());
+            assert_eq!(sorted.contains(&item), expected.contains(&item));
+        }
+    }
+
+    #[test]
+    fn test_remove_and_delete() {
+        let mut sorted = vector![4, 2, 2, 8, 2, 6].into_sorted();
+        assert_eq!(sorted.remove(&2), 3);
+        assert_eq!(sorted.remove(&3), 0);
+        assert_eq!(sorted.as_slice(), &[4, 6, 8]);
+        assert_eq!(sorted.delete(1), 6);
+        assert_eq!(sorted.pop(), Some(8));
+        assert_eq!(sorted.into_inner(), [4]);
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut left = vector![1, 4, 4, 9].into_sorted();
+        left.merge(vector![0, 4, 5, 10, 11].into_sorted());
+        assert_eq!(left.as_slice(), &[0, 1, 4, 4, 4, 5, 9, 10, 11]);
+
+        let mut empty = SortedVector::new(0);
+        empty.merge(left.clone());
+        assert_eq!(empty, left);
+        left.merge(SortedVector::new(0));
+        assert_eq!(left, empty);
+
+        // Of equal elements, the ones already in the vector come first
+        let mut left = SortedVector::new(0);
+        let mut right = SortedVector::new(0);
+        for item in [Tagged(2, 'a'), Tagged(1, 'a'), Tagged(2, 'b')] {
+            left.insert(item);
+        }
+        for item in [Tagged(2, 'c'), Tagged(3, 'c')] {
+            right.insert(item);
+        }
+        left.merge(right);
+        let tags: String = left.iter().map(|t| t.1).collect();
+        assert_eq!(tags, "aabcc");
+    }
+
+    #[test]
+    fn test_vectors_from_other_allocators() {
+        let alloc = CountingAllocator::new();
+        let mut vec = Vector::new_in(0, alloc.clone());
+        vec.extend_from_slice(&[5, 1, 4]);
+        let mut sorted = vec.into_sorted();
+        sorted.insert(3);
+
+        // Merging in a vector from another allocator moves its elements into this one's buffer
+        let mut other = SortedVector::with_policy_in(0, Doubling, CountingAllocator::new());
+        other.insert(2);
+        sorted.merge(other);
+        sorted.merge(vector![0, 6].into_sorted());
+        assert_eq!(sorted.as_slice(), &[0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(alloc.live_bytes(), sorted.capacity() * std::mem::size_of::<i32>());
+        drop(sorted);
+        assert_eq!(alloc.live_bytes(), 0);
+    }
+
+    #[test]
+    fn test_merge_with_panicking_comparison_keeps_every_element() {
+        // An element type whose comparison panics once the merge is under way
+        thread_local!(static COMPARISONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) });
+        #[derive(PartialEq, Eq)]
+        struct Fragile(i32, Rc<()>);
+        impl PartialOrd for Fragile {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Fragile {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                let count = COMPARISONS.with(|c| c.replace(c.get() + 1));
+                assert!(count != 25, "comparison failed");
+                self.0.cmp(&other.0)
+            }
+        }
+
+        // Building the inputs compares too, so the count only starts for the merge
+        COMPARISONS.with(|c| c.set(usize::MAX / 2));
+        let tracker = Rc::new(());
+        let mut left: SortedVector<Fragile> = (0..20).map(|i| Fragile(i * 2, Rc::clone(&tracker))).collect();
+        let right: SortedVector<Fragile> = (0..20).map(|i| Fragile(i * 2 + 1, Rc::clone(&tracker))).collect();
+        COMPARISONS.with(|c| c.set(0));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| left.merge(right)));
+        assert!(result.is_err());
+        assert_eq!(left.size(), 40);
+        drop(left);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
+}
@@ -0,0 +1,203 @@
+// Process-wide allocation statistics and resize events, enabled by the `stats` feature:
+// * snapshot() - counters for every buffer allocated and freed by the crate's collections
+//     (`Vector` and everything built on its allocation, such as `Deque` and the sort scratch buffers)
+// * reset() - sets every counter back to zero, e.g. between dashboard scrapes
+// * set_resize_hook(hook), clear_resize_hook() - a callback fired each time a `Vector` grows or
+//     shrinks, with the old and new capacity
+//
+// Counters are updated with relaxed atomics, so reading them never blocks a vector, but a snapshot
+// taken while other threads allocate may mix counts from slightly different moments.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static DEALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static BYTES_ALLOCATED: AtomicU64 = AtomicU64::new(0);
+static BYTES_FREED: AtomicU64 = AtomicU64::new(0);
+static BYTES_COPIED: AtomicU64 = AtomicU64::new(0);
+static PEAK_CAPACITY_BYTES: AtomicU64 = AtomicU64::new(0);
+
+// Callback run for every resize, if one is registered
+type ResizeHook = Arc<dyn Fn(&ResizeEvent) + Send + Sync>;
+
+static RESIZE_HOOK: RwLock<Option<ResizeHook>> = RwLock::new(None);
+
+// Counters at one point in time
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub allocations: u64,         // Buffers allocated
+    pub deallocations: u64,       // Buffers freed
+    pub bytes_allocated: u64,     // Total size of the buffers allocated
+    pub bytes_freed: u64,         // Total size of the buffers freed
+    pub bytes_copied: u64,        // Bytes of elements moved from an old buffer to a new one by `Vector` resizes
+    pub peak_capacity_bytes: u64, // Size of the largest buffer allocated
+}
+
+impl Stats {
+    // Returns the number of bytes allocated and not yet freed
+    pub fn live_bytes(&self) -> u64 {
+        self.bytes_allocated.saturating_sub(self.bytes_freed)
+    }
+}
+
+// A `Vector` moving its elements to a buffer of a different capacity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResizeEvent {
+    pub old_capacity: usize, // Capacity before the resize, in elements
+    pub new_capacity: usize, // Capacity after the resize, in elements
+    pub size: usize,         // Number of elements moved
+    pub element_size: usize, // Size of one element in bytes
+}
+
+impl ResizeEvent {
+    // Checks if the vector grew
+    pub fn is_grow(&self) -> bool {
+        self.new_capacity > self.old_capacity
+    }
+}
+
+// Returns the current value of every counter
+pub fn snapshot() -> Stats {
+    Stats {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        deallocations: DEALLOCATIONS.load(Ordering::Relaxed),
+        bytes_allocated: BYTES_ALLOCATED.load(Ordering::Relaxed),
+        bytes_freed: BYTES_FREED.load(Ordering::Relaxed),
+        bytes_copied: BYTES_COPIED.load(Ordering::Relaxed),
+        peak_capacity_bytes: PEAK_CAPACITY_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+// Sets every counter back to zero; buffers allocated before the reset and freed after it make
+// `bytes_freed` run ahead of `bytes_allocated`
+pub fn reset() {
+    for counter in [&ALLOCATIONS, &DEALLOCATIONS, &BYTES_ALLOCATED, &BYTES_FREED, &BYTES_COPIED, &PEAK_CAPACITY_BYTES] {
+        counter.store(0, Ordering::Relaxed);
+    }
+}
+
+// Registers a callback run after every `Vector` resize, replacing any previous one. It runs on the
+// thread that resized the vector, which may resize vectors itself.
+pub fn set_resize_hook<F: Fn(&ResizeEvent) + Send + Sync + 'static>(hook: F) {
+    *RESIZE_HOOK.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(Arc::new(hook));
+}
+
+// Removes the resize callback
+pub fn clear_resize_hook() {
+    *RESIZE_HOOK.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+}
+
+// Counts a buffer of `bytes` bytes being allocated
+pub(crate) fn record_allocation(bytes: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    BYTES_ALLOCATED.fetch_add(bytes as u64, Ordering::Relaxed);
+    PEAK_CAPACITY_BYTES.fetch_max(bytes as u64, Ordering::Relaxed);
+}
+
+// Counts a buffer of `bytes` bytes being freed
+pub(crate) fn record_deallocation(bytes: usize) {
+    DEALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    BYTES_FREED.fetch_add(bytes as u64, Ordering::Relaxed);
+}
+
+// Counts the bytes a resize copied and runs the resize callback
+pub(crate) fn record_resize(event: ResizeEvent) {
+    BYTES_COPIED.fetch_add((event.size * event.element_size) as u64, Ordering::Relaxed);
+
+    // The lock is released before the callback runs, so the callback may resize vectors too
+    let hook = RESIZE_HOOK.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone();
+    if let Some(hook) = hook {
+        hook(&event);
+    }
+}
+
+// Unit tests for the statistics; other tests allocate concurrently, so counters are checked for
+// at least the expected increase and events are filtered by thread
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Vector;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::Mutex;
+    use std::thread;
+
+    // The resize hook is global, so tests that set it take turns
+    static HOOK_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_counters() {
+        let before = snapshot();
+        {
+            let mut vec: Vector<u64> = Vector::new(0);
+            for i in 0..100 {
+                vec.push(i);
+            }
+        }
+        let after = snapshot();
+
+        // 16 -> 32 -> 64 -> 128 allocates four buffers and frees them all, copying 16 + 32 + 64 elements
+        assert!(after.allocations - before.allocations >= 4);
+        assert!(after.deallocations - before.deallocations >= 4);
+        assert!(after.bytes_allocated - before.bytes_allocated >= (16 + 32 + 64 + 128) * 8);
+        assert!(after.bytes_copied - before.bytes_copied >= (16 + 32 + 64) * 8);
+        assert!(after.peak_capacity_bytes >= 128 * 8);
+    }
+
+    #[test]
+    fn test_resize_hook() {
+        let _turn = HOOK_LOCK.lock().unwrap();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let this_thread = thread::current().id();
+        {
+            let events = Arc::clone(&events);
+            set_resize_hook(move |event| {
+                if thread::current().id() == this_thread {
+                    events.lock().unwrap().push(*event);
+                }
+            });
+        }
+
+        let mut vec: Vector<u32> = Vector::new(0);
+        for i in 0..40 {
+            vec.push(i);
+        }
+        while vec.size() > 5 {
+            vec.pop();
+        }
+        clear_resize_hook();
+        for i in 0..40 {
+            vec.push(i);
+        }
+
+        let events = events.lock().unwrap();
+        let capacities: Vec<(usize, usize)> = events.iter().map(|e| (e.old_capacity, e.new_capacity)).collect();
+        assert_eq!(capacities, [(16, 32), (32, 64), (64, 32), (32, 16)]);
+        assert!(events[0].is_grow() && !events[2].is_grow());
+        assert_eq!((events[0].size, events[0].element_size), (16, 4));
+    }
+
+    #[test]
+    fn test_panicking_resize_hook() {
+        let _turn = HOOK_LOCK.lock().unwrap();
+        let this_thread = thread::current().id();
+        set_resize_hook(move |_| {
+            if thread::current().id() == this_thread {
+                panic!("resize hook failed");
+            }
+        });
+
+        // The panic escapes the push that grows the buffer, but the vector already owns the new one
+        let mut vec: Vector<String> = (0..16).map(|i| i.to_string()).collect();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| vec.push("16".to_string())));
+        clear_resize_hook();
+        assert!(result.is_err());
+        assert_eq!(vec.size(), 16);
+        assert_eq!(vec.capacity(), 32);
+
+        for i in 16..40 {
+            vec.push(i.to_string());
+        }
+        assert!(vec.iter().enumerate().all(|(i, item)| *item == i.to_string()));
+    }
+}
@@ -1,4 +1,4 @@
-// Implement a vector (mutable array with automatic resizing):
+// Implement a vector (mutable array with automatic resizing), generic over its element type:
 // * New raw data array with allocated memory
 //     can allocate int array under the hood, just not use its features
 //     start with 16, or if the starting number is greater, use power of 2 - 16, 32, 64, 128
@@ -6,39 +6,208 @@
 // * capacity() - number of items it can hold
 // * is_empty()
 // * at(index) - returns the item at a given index, blows up if index out of bounds
+// * get(index), get_mut(index) - like at(index), but return None if index out of bounds
 // * push(item)
 // * insert(index, item) - inserts item at index, shifts that index's value and trailing elements to the right
 // * prepend(item) - can use insert above at index 0
 // * pop() - remove from end, return value
 // * delete(index) - delete item at index, shifting all trailing elements left
 // * remove(item) - looks for value and removes index holding it (even if in multiple places)
-// * find(item) - looks for value and returns first index with that value, -1 if not found
+// * find(item) - looks for value and returns first index with that value, None if not found
+// * count(item), contains(item), simd_find(item), simd_remove(item), minimum(), maximum(), sum() - for
+//     Vector<i32>; scan 4 or 8 items per instruction on x86_64 (see `simd`)
+// * try_push(item), try_insert(index, item), try_delete(index) - return a VectorError instead of blowing up
+// * as_slice(), as_mut_slice(), vec[index], vec[range] - slice access to the elements
+// * iter(), iter_mut(), into_iter() - borrowing, mutable and owning iterators
+// * collect() and extend() - build or grow a vector from any iterator
+// * with_capacity(n) - start with at least n slots, keeping the 16-minimum, power-of-two rule
+// * reserve(n), reserve_exact(n) - make room for n more items up front
+// * shrink_to(n), shrink_to_fit() - release unused capacity
+// * truncate(len), clear() - drop trailing or all items, shrinking like pop()
+// * try_with_capacity(n), try_reserve(n), try_reserve_exact(n) - allocation failures and capacity
+//     overflow are reported as a VectorError instead of dereferencing a null buffer
+// * extend_from_slice(items), insert_slice(index, items) - copy in many items with one shift
+// * drain(range), splice(range, items) - take out or replace a range with one shift
+// * retain(keep), dedup(), dedup_by_key(key) - filter in a single pass
+// * vector![1, 2, 3], vector![0; n] - build a vector in one line
+// * Clone, Debug, Display, Default, ==, <, Hash - compare and print vectors like slices
+// * From/Into arrays, slices and Vec
+// * Send and Sync whenever the element type is - move a vector into a thread or share it behind an Arc
+// * freeze() - turn into a read-only FrozenVector that is cheap to clone across threads
+// * into_sorted() - sort once and turn into a SortedVector with binary search and ordered insert
+// * with_policy(n, policy) - choose how capacity grows and shrinks (see `growth`)
+// * new_in(n, alloc), with_capacity_in(n, alloc), with_policy_in(n, policy, alloc) - take the buffer
+//     from another allocator, e.g. a `CountingAllocator` or a `BumpAllocator` (see `allocator`)
+// * with the `stats` feature, every allocation, copy and resize is counted (see `stats`)
 // * resize(new_capacity) // private function
-//     when you reach capacity, resize to double the size
-//     when popping an item, if the size is 1/4 of capacity, resize to half
+//     when you reach capacity, resize to the capacity chosen by the growth policy (double the size by default)
+//     when popping or deleting an item, ask the growth policy whether to shrink (to half once the size is 1/4 of capacity by default)
 
-use std::alloc::{alloc, dealloc, Layout};
+use crate::allocator::{Allocator, Global};
+use crate::frozen::FrozenVector;
+use crate::growth::{Doubling, GrowthPolicy, MIN_CAPACITY};
+use crate::simd;
+use crate::sorted::SortedVector;
+use std::alloc::{handle_alloc_error, Layout};
+use std::error::Error;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::ops::{Bound, Deref, DerefMut, Index, IndexMut, Range, RangeBounds};
+use std::ptr::{self, NonNull};
+use std::slice::{self, SliceIndex};
 
-// Define a `Vector` struct with a raw pointer to data, size, and capacity
-pub struct Vector {
-    data: *mut i32,  // Raw pointer to a dynamically allocated array of i32
+// Errors returned by the non-panicking `try_*` operations of `Vector`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VectorError {
+    // The index was not smaller than the number of elements
+    IndexOutOfBounds { index: usize, len: usize },
+    // The requested capacity does not fit in `usize` or exceeds `isize::MAX` bytes
+    CapacityOverflow,
+    // The allocator could not provide memory for the requested layout
+    AllocFailed { layout: Layout },
+}
+
+impl fmt::Display for VectorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VectorError::IndexOutOfBounds { index, len } => {
+                write!(f, "Index out of bounds: the len is {} but the index is {}", len, index)
+            }
+            VectorError::CapacityOverflow => write!(f, "Capacity overflow"),
+            VectorError::AllocFailed { layout } => {
+                write!(f, "Memory allocation of {} bytes failed", layout.size())
+            }
+        }
+    }
+}
+
+impl Error for VectorError {}
+
+impl VectorError {
+    // Reports the error the way the standard collections do: allocation failures go through the
+    // global allocation error handler, everything else panics
+    pub(crate) fn raise(self) -> ! {
+        match self {
+            VectorError::AllocFailed { layout } => handle_alloc_error(layout),
+            err => panic!("{}", err),
+        }
+    }
+}
+
+// Define a `Vector` struct with a raw pointer to data, size, capacity, the policy deciding how capacity changes
+// and the allocator the data comes from
+pub struct Vector<T, P = Doubling, A: Allocator = Global> {
+    data: *mut T,    // Raw pointer to a dynamically allocated array of T
     size: usize,     // Current number of elements in the vector
     capacity: usize, // Maximum number of elements the vector can hold without resizing
+    policy: P,       // Growth and shrink policy, chosen at construction
+    alloc: A,        // Allocator owning `data`, chosen at construction
 }
 
-impl Vector {
+impl<T> Vector<T> {
     // Creates a new `Vector` with an initial capacity, defaulting to 16 if 0 is provided
     pub fn new(initial_capacity: usize) -> Self {
+        Self::with_policy(initial_capacity, Doubling)
+    }
+
+    // Creates a new `Vector` that can hold at least `capacity` elements without resizing;
+    // the capacity is at least 16 and a power of two
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_policy(capacity, Doubling)
+    }
+
+    // Creates a new `Vector` like `with_capacity`, but returns an error if the memory cannot be allocated
+    pub fn try_with_capacity(initial_capacity: usize) -> Result<Self, VectorError> {
+        Self::try_with_policy(initial_capacity, Doubling)
+    }
+
+    // Creates a new `Vector` holding `n` clones of `item`, used by `vector![item; n]`
+    pub fn from_elem(item: T, n: usize) -> Self
+    where
+        T: Clone,
+    {
+        let mut vec = Self::with_capacity(n);
+        if n > 0 {
+            for _ in 1..n {
+                vec.push(item.clone());
+            }
+            vec.push(item);
+        }
+        vec
+    }
+}
+
+impl<T, A: Allocator> Vector<T, Doubling, A> {
+    // Creates a new `Vector` like `new`, taking its buffer from `alloc`
+    pub fn new_in(initial_capacity: usize, alloc: A) -> Self {
+        Self::with_policy_in(initial_capacity, Doubling, alloc)
+    }
+
+    // Creates a new `Vector` like `with_capacity`, taking its buffer from `alloc`
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self::with_policy_in(capacity, Doubling, alloc)
+    }
+
+    // Creates a new `Vector` like `with_capacity_in`, but returns an error if the memory cannot be allocated
+    pub fn try_with_capacity_in(initial_capacity: usize, alloc: A) -> Result<Self, VectorError> {
+        Self::try_with_policy_in(initial_capacity, Doubling, alloc)
+    }
+}
+
+impl<T, P: GrowthPolicy> Vector<T, P> {
+    // Creates a new `Vector` like `new`, growing and shrinking according to `policy`
+    pub fn with_policy(initial_capacity: usize, policy: P) -> Self {
+        Self::with_policy_in(initial_capacity, policy, Global)
+    }
+
+    // Creates a new `Vector` like `with_policy`, but returns an error if the memory cannot be allocated
+    pub fn try_with_policy(initial_capacity: usize, policy: P) -> Result<Self, VectorError> {
+        Self::try_with_policy_in(initial_capacity, policy, Global)
+    }
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> Vector<T, P, A> {
+    // Creates a new `Vector` like `with_policy`, taking its buffer from `alloc`
+    pub fn with_policy_in(initial_capacity: usize, policy: P, alloc: A) -> Self {
+        Self::try_with_policy_in(initial_capacity, policy, alloc).unwrap_or_else(|err| err.raise())
+    }
+
+    // Creates a new `Vector` like `with_policy_in`, but returns an error if the memory cannot be allocated
+    pub fn try_with_policy_in(initial_capacity: usize, policy: P, alloc: A) -> Result<Self, VectorError> {
         // Ensure capacity is at least 16 and is a power of two
-        let capacity = if initial_capacity > 0 {
-            initial_capacity.next_power_of_two()
-        } else {
-            16
-        };
+        let capacity = capacity_for(initial_capacity)?;
 
         // Allocate memory for the vector, ensuring proper layout
-        let data = unsafe { alloc(Layout::array::<i32>(capacity).unwrap()) as *mut i32 };
-        Vector { data, size: 0, capacity }
+        let data = try_allocate_in(&alloc, capacity)?;
+        Ok(Vector { data, size: 0, capacity, policy, alloc })
+    }
+
+    // Returns the policy deciding how the capacity changes
+    pub fn policy(&self) -> &P {
+        &self.policy
+    }
+
+    // Returns the allocator the buffer comes from
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    // Turns the vector into a read-only `FrozenVector` that can be cloned cheaply and shared
+    // across threads; the elements are not copied
+    pub fn freeze(self) -> FrozenVector<T, P, A> {
+        FrozenVector::from(self)
+    }
+
+    // Sorts the vector once and turns it into a `SortedVector` that keeps it sorted
+    pub fn into_sorted(self) -> SortedVector<T, P, A>
+    where
+        T: Ord,
+    {
+        SortedVector::from(self)
     }
 
     // Returns the current number of elements in the vector
@@ -56,136 +225,947 @@ impl Vector {
         self.size == 0
     }
 
-    // Returns the element at a given index, panics if the index is out of bounds
-    pub fn at(&self, index: usize) -> i32 {
+    // Returns a copy of the element at a given index, panics if the index is out of bounds
+    pub fn at(&self, index: usize) -> T
+    where
+        T: Clone,
+    {
+        match self.get(index) {
+            Some(item) => item.clone(),
+            None => VectorError::IndexOutOfBounds { index, len: self.size }.raise(),
+        }
+    }
+
+    // Returns a reference to the element at a given index, or `None` if the index is out of bounds
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index >= self.size {
+            return None;
+        }
+        Some(unsafe { &*self.data.add(index) })
+    }
+
+    // Returns a mutable reference to the element at a given index, or `None` if the index is out of bounds
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
         if index >= self.size {
-            panic!("Index out of bounds");
+            return None;
+        }
+        Some(unsafe { &mut *self.data.add(index) })
+    }
+
+    // Adds a new element to the end of the vector, panics if the buffer cannot grow
+    pub fn push(&mut self, item: T) {
+        if let Err(err) = self.try_push(item) {
+            err.raise();
         }
-        // Return the value at the specified index (unsafe due to raw pointer manipulation)
-        unsafe { *self.data.add(index) }
     }
 
     // Adds a new element to the end of the vector, resizing if necessary
-    pub fn push(&mut self, item: i32) {
-        // Resize if capacity is full
+    pub fn try_push(&mut self, item: T) -> Result<(), VectorError> {
+        // Grow if capacity is full
         if self.size == self.capacity {
-            self.resize(self.capacity * 2);
+            self.grow(self.size + 1)?;
         }
-        // Add the item to the end and increase the size
-        unsafe { *self.data.add(self.size) = item; }
+        // Move the item into the first free slot and increase the size
+        unsafe { ptr::write(self.data.add(self.size), item); }
         self.size += 1;
+        Ok(())
+    }
+
+    // Inserts an element at a specified index, panics if the index is out of bounds
+    pub fn insert(&mut self, index: usize, item: T) {
+        if let Err(err) = self.try_insert(index, item) {
+            err.raise();
+        }
     }
 
     // Inserts an element at a specified index, shifting existing elements
-    pub fn insert(&mut self, index: usize, item: i32) {
+    pub fn try_insert(&mut self, index: usize, item: T) -> Result<(), VectorError> {
         if index >= self.size {
-            panic!("Index out of bounds");
+            return Err(VectorError::IndexOutOfBounds { index, len: self.size });
         }
 
-        // Resize if capacity is full
+        // Grow if capacity is full
         if self.size == self.capacity {
-            self.resize(self.capacity * 2);
+            self.grow(self.size + 1)?;
         }
 
-        // Shift elements to the right starting from the specified index
-        for i in (index..self.size).rev() {
-            unsafe { *self.data.add(i + 1) = *self.data.add(i); }
+        // Shift elements to the right starting from the specified index, then move the new item in
+        unsafe {
+            ptr::copy(self.data.add(index), self.data.add(index + 1), self.size - index);
+            ptr::write(self.data.add(index), item);
         }
-
-        // Insert the new item at the specified index
-        unsafe { *self.data.add(index) = item; }
         self.size += 1;
+        Ok(())
     }
 
     // Inserts an element at the beginning of the vector
-    pub fn prepend(&mut self, item: i32) {
+    pub fn prepend(&mut self, item: T) {
         self.insert(0, item);
     }
 
     // Removes and returns the last element, resizing if necessary
-    pub fn pop(&mut self) -> Option<i32> {
+    pub fn pop(&mut self) -> Option<T> {
         if self.is_empty() {
             return None;
         }
 
-        // Get the last element
-        let value = unsafe { *self.data.add(self.size - 1) };
+        // Move the last element out; its slot is no longer considered initialized
         self.size -= 1;
+        let value = unsafe { ptr::read(self.data.add(self.size)) };
 
-        // Shrink capacity if the size is much smaller than capacity, with a minimum of 16
-        if self.size <= self.capacity / 4 && self.capacity > 16 {
-            self.resize(self.capacity / 2);
-        }
+        self.shrink();
 
         Some(value)
     }
 
-    // Deletes the element at a specified index and shifts the remaining elements
+    // Deletes the element at a specified index, panics if the index is out of bounds
     pub fn delete(&mut self, index: usize) {
-        if index >= self.size {
-            panic!("Index out of bounds");
+        if let Err(err) = self.try_delete(index) {
+            err.raise();
         }
+    }
 
-        // Shift elements to the left to fill the gap
-        for i in index..self.size - 1 {
-            unsafe { *self.data.add(i) = *self.data.add(i + 1); }
+    // Deletes the element at a specified index and shifts the remaining elements,
+    // returning the deleted element
+    pub fn try_delete(&mut self, index: usize) -> Result<T, VectorError> {
+        if index >= self.size {
+            return Err(VectorError::IndexOutOfBounds { index, len: self.size });
         }
 
+        // Move the element out, then shift elements to the left to fill the gap
+        let value = unsafe {
+            let value = ptr::read(self.data.add(index));
+            ptr::copy(self.data.add(index + 1), self.data.add(index), self.size - index - 1);
+            value
+        };
+
         self.size -= 1;
 
-        // Resize if necessary
-        if self.size <= self.capacity / 4 && self.capacity > 16 {
-            self.resize(self.capacity * 2);
+        self.shrink();
+
+        Ok(value)
+    }
+
+    // Removes all occurrences of a specified item from the vector in a single pass
+    pub fn remove(&mut self, item: &T)
+    where
+        T: PartialEq,
+    {
+        self.retain(|x| x != item);
+    }
+
+    // Appends clones of all items in a slice, reallocating at most once
+    pub fn extend_from_slice(&mut self, items: &[T])
+    where
+        T: Clone,
+    {
+        self.reserve(items.len());
+        for item in items {
+            // Grow the size one item at a time, so a panicking `clone` leaves a consistent vector
+            unsafe { ptr::write(self.data.add(self.size), item.clone()); }
+            self.size += 1;
         }
     }
 
-    // Removes all occurrences of a specified item from the vector
-    pub fn remove(&mut self, item: i32) {
-        let mut i = 0;
+    // Inserts clones of all items in a slice at a specified index, shifting the trailing elements
+    // right once; unlike `insert`, the index may also equal the size to append
+    pub fn insert_slice(&mut self, index: usize, items: &[T])
+    where
+        T: Clone,
+    {
+        if index > self.size {
+            VectorError::IndexOutOfBounds { index, len: self.size }.raise();
+        }
+        self.reserve(items.len());
+
+        let len = self.size;
+        unsafe {
+            // Open a gap for the new items in one move
+            ptr::copy(self.data.add(index), self.data.add(index + items.len()), len - index);
 
-        // Iterate through the vector and delete occurrences of the item
-        while i < self.size {
-            if unsafe { *self.data.add(i) == item } {
-                self.delete(i);
-            } else {
-                i += 1;
+            // While the gap is being filled only the leading elements count as live, so a panicking
+            // `clone` leaks the trailing elements instead of dropping uninitialized memory
+            self.size = index;
+            for (offset, item) in items.iter().enumerate() {
+                ptr::write(self.data.add(index + offset), item.clone());
             }
         }
+        self.size = len + items.len();
+    }
+
+    // Removes the elements in a range and returns them as an iterator; the trailing elements are
+    // moved left once, when the iterator is dropped, even if it was not fully consumed
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T, P, A> {
+        let Range { start, end } = self.range(range);
+        let len = self.size;
+
+        // Only the leading elements count as live until the drain is dropped
+        self.size = start;
+        Drain { vec: self, start, next: start, end, tail: end, tail_len: len - end }
     }
 
-    // Finds the index of the first occurrence of an item, returns -1 if not found
-    pub fn find(&self, item: i32) -> isize {
-        for i in 0..self.size {
-            if unsafe { *self.data.add(i) == item } {
-                return i as isize;
+    // Replaces the elements in a range with the items of an iterator and returns the removed
+    // elements, allocated from the same allocator; the trailing elements are moved at most twice and
+    // the buffer reallocated at most once, because the iterator reports its length up front. Items
+    // beyond that length are ignored.
+    pub fn splice<R, I>(&mut self, range: R, replace_with: I) -> Vector<T, Doubling, A>
+    where
+        R: RangeBounds<usize>,
+        I: IntoIterator<Item = T>,
+        I::IntoIter: ExactSizeIterator,
+        A: Clone,
+    {
+        let Range { start, end } = self.range(range);
+        let mut items = replace_with.into_iter();
+        let count = items.len();
+        let tail_len = self.size - end;
+
+        // Grow once for the final length
+        let new_len = (start + tail_len).checked_add(count).unwrap_or_else(|| VectorError::CapacityOverflow.raise());
+        if new_len > self.capacity {
+            if let Err(err) = self.grow(new_len) {
+                err.raise();
             }
         }
 
-        -1
+        // Move the replaced elements out, then move the tail to its final position
+        let mut removed = Vector::with_capacity_in(end - start, self.alloc.clone());
+        unsafe {
+            ptr::copy_nonoverlapping(self.data.add(start), removed.data, end - start);
+            removed.size = end - start;
+            ptr::copy(self.data.add(end), self.data.add(start + count), tail_len);
+        }
+
+        // Fill the gap; only the leading elements count as live meanwhile, so a panicking iterator
+        // leaks the trailing elements instead of dropping uninitialized memory
+        self.size = start;
+        let mut written = 0;
+        for item in items.by_ref().take(count) {
+            unsafe { ptr::write(self.data.add(start + written), item) };
+            written += 1;
+        }
+
+        // Close the part of the gap an iterator that reported too many items left open
+        if written < count {
+            unsafe { ptr::copy(self.data.add(start + count), self.data.add(start + written), tail_len) };
+        }
+        self.size = start + written + tail_len;
+        self.shrink();
+        removed
     }
 
-    // Resizes the vector's capacity and reallocates its data
-    fn resize(&mut self, new_capacity: usize) {
-        // Allocate new memory with the new capacity
-        let new_data = unsafe { alloc(Layout::array::<i32>(new_capacity).unwrap()) as *mut i32 };
+    // Keeps only the elements for which `keep` returns true, moving each kept element at most once
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        let len = self.size;
+        let mut deleted = 0;
+
+        // No element counts as live during the pass, so a panicking `keep` or destructor leaks the
+        // elements instead of dropping them twice
+        self.size = 0;
+        for i in 0..len {
+            unsafe {
+                let item = self.data.add(i);
+                if !keep(&*item) {
+                    ptr::drop_in_place(item);
+                    deleted += 1;
+                } else if deleted > 0 {
+                    ptr::copy_nonoverlapping(item, self.data.add(i - deleted), 1);
+                }
+            }
+        }
+        self.size = len - deleted;
+        self.shrink();
+    }
+
+    // Removes consecutive repeated elements, keeping the first of each run
+    pub fn dedup(&mut self)
+    where
+        T: PartialEq,
+    {
+        self.dedup_by(|a, b| a == b);
+    }
+
+    // Removes consecutive elements that map to the same key, keeping the first of each run
+    pub fn dedup_by_key<K, F>(&mut self, mut key: F)
+    where
+        K: PartialEq,
+        F: FnMut(&mut T) -> K,
+    {
+        self.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    // Removes consecutive elements for which `same_bucket(element, previous_kept)` returns true,
+    // moving each kept element at most once
+    pub fn dedup_by<F>(&mut self, mut same_bucket: F)
+    where
+        F: FnMut(&mut T, &mut T) -> bool,
+    {
+        let len = self.size;
+        if len <= 1 {
+            return;
+        }
+
+        // No element counts as live during the pass, so a panicking `same_bucket` or destructor
+        // leaks the elements instead of dropping them twice
+        self.size = 0;
+        let mut kept = 1;
+        for i in 1..len {
+            unsafe {
+                let item = self.data.add(i);
+                if same_bucket(&mut *item, &mut *self.data.add(kept - 1)) {
+                    ptr::drop_in_place(item);
+                } else {
+                    if kept != i {
+                        ptr::copy_nonoverlapping(item, self.data.add(kept), 1);
+                    }
+                    kept += 1;
+                }
+            }
+        }
+        self.size = kept;
+        self.shrink();
+    }
+
+    // Finds the index of the first occurrence of an item, returns `None` if not found
+    pub fn find(&self, item: &T) -> Option<usize>
+    where
+        T: PartialEq,
+    {
+        (0..self.size).find(|&i| unsafe { &*self.data.add(i) == item })
+    }
+
+    // Returns the initialized part of the buffer as a slice
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.data, self.size) }
+    }
+
+    // Returns the initialized part of the buffer as a mutable slice
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.data, self.size) }
+    }
+
+    // Returns an iterator over references to the elements, front to back
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.as_slice().iter()
+    }
+
+    // Returns an iterator over mutable references to the elements, front to back
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
+        self.as_mut_slice().iter_mut()
+    }
+
+    // Makes room for at least `additional` more elements, panics if the buffer cannot grow
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(err) = self.try_reserve(additional) {
+            err.raise();
+        }
+    }
+
+    // Makes room for at least `additional` more elements with a single reallocation sized by the
+    // growth policy, or returns an error and leaves the vector untouched
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), VectorError> {
+        let required = self.size.checked_add(additional).ok_or(VectorError::CapacityOverflow)?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+        self.grow(required)
+    }
+
+    // Makes room for exactly `additional` more elements, ignoring the growth policy,
+    // panics if the buffer cannot grow
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Err(err) = self.try_reserve_exact(additional) {
+            err.raise();
+        }
+    }
+
+    // Makes room for exactly `additional` more elements, or returns an error and leaves the vector untouched
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), VectorError> {
+        let required = self.size.checked_add(additional).ok_or(VectorError::CapacityOverflow)?;
+        if required <= self.capacity {
+            return Ok(());
+        }
+        self.resize(required)
+    }
+
+    // Shrinks the capacity to `min_capacity`, or to the number of elements if that is larger
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        let new_capacity = min_capacity.max(self.size);
+        if new_capacity < self.capacity {
+            let _ = self.resize(new_capacity);
+        }
+    }
+
+    // Shrinks the capacity to the number of elements
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0);
+    }
+
+    // Keeps the first `len` elements and drops the rest, then shrinks like `pop` does
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.size {
+            return;
+        }
+        let tail = ptr::slice_from_raw_parts_mut(unsafe { self.data.add(len) }, self.size - len);
+
+        // Shorten the vector before dropping, so a panicking destructor cannot cause a double drop
+        self.size = len;
+        unsafe { ptr::drop_in_place(tail) };
+
+        self.shrink();
+    }
+
+    // Drops every element, then shrinks like `pop` does
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
 
-        // Copy elements from the old memory to the new memory
-        for i in 0..self.size {
-            unsafe { *new_data.add(i) = *self.data.add(i); }
+    // Resolves a range of positions against the current size, panics if it is out of bounds
+    fn range<R: RangeBounds<usize>>(&self, range: R) -> Range<usize> {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.checked_add(1).unwrap_or_else(|| VectorError::CapacityOverflow.raise()),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end.checked_add(1).unwrap_or_else(|| VectorError::CapacityOverflow.raise()),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => self.size,
+        };
+        if end > self.size {
+            VectorError::IndexOutOfBounds { index: end, len: self.size }.raise();
+        }
+        if start > end {
+            panic!("Range starts at {} but ends at {}", start, end);
         }
+        start..end
+    }
+
+    // Asks the growth policy for a capacity that fits `required` elements and reallocates to it,
+    // failing instead of overflowing when the buffer is already at its limit
+    fn grow(&mut self, required: usize) -> Result<(), VectorError> {
+        let new_capacity = self.policy.grow(self.capacity, required).ok_or(VectorError::CapacityOverflow)?;
+        self.resize(new_capacity.max(required))
+    }
+
+    // Asks the growth policy whether the buffer should shrink after elements were removed, repeating
+    // until it is satisfied so that bulk removals end up where one-by-one removals would, but
+    // reallocating only once; shrinking is only an optimization, so keep the larger buffer if the
+    // allocation fails
+    fn shrink(&mut self) {
+        let mut new_capacity = self.capacity;
+        while let Some(capacity) = self.policy.shrink(self.size, new_capacity) {
+            if capacity < self.size || capacity >= new_capacity {
+                break;
+            }
+            new_capacity = capacity;
+        }
+        if new_capacity < self.capacity {
+            let _ = self.resize(new_capacity);
+        }
+    }
+
+    // Returns the start of the buffer, for other modules that fill the spare capacity directly
+    pub(crate) fn raw_data(&mut self) -> *mut T {
+        self.data
+    }
+
+    // Sets the number of initialized elements; the caller guarantees that the first `size`
+    // elements are initialized and that `size` fits the capacity
+    pub(crate) unsafe fn set_size(&mut self, size: usize) {
+        self.size = size;
+    }
+
+    // Resizes the vector's capacity and reallocates its data, leaving the vector untouched on failure
+    fn resize(&mut self, new_capacity: usize) -> Result<(), VectorError> {
+        // Allocate new memory with the new capacity
+        let new_data = try_allocate_in(&self.alloc, new_capacity)?;
+
+        // Move elements from the old memory to the new memory
+        unsafe { ptr::copy_nonoverlapping(self.data, new_data, self.size); }
 
         // Deallocate the old memory
-        unsafe { dealloc(self.data as *mut u8, Layout::array::<i32>(self.capacity).unwrap()) };
+        unsafe { deallocate_in(&self.alloc, self.data, self.capacity) };
 
+        #[cfg(feature = "stats")]
+        let old_capacity = self.capacity;
         self.data = new_data;
         self.capacity = new_capacity;
+
+        // Report the resize only once the vector owns the new buffer, so a panicking hook cannot
+        // leave it pointing at the freed one
+        #[cfg(feature = "stats")]
+        crate::stats::record_resize(crate::stats::ResizeEvent {
+            old_capacity,
+            new_capacity,
+            size: self.size,
+            element_size: std::mem::size_of::<T>(),
+        });
+        Ok(())
+    }
+}
+
+// Rounds a requested capacity up to the next power of two, with a minimum of 16
+pub(crate) fn capacity_for(requested: usize) -> Result<usize, VectorError> {
+    requested.max(MIN_CAPACITY).checked_next_power_of_two().ok_or(VectorError::CapacityOverflow)
+}
+
+// Allocates an uninitialized buffer for `capacity` elements from the global allocator
+pub(crate) fn try_allocate<T>(capacity: usize) -> Result<*mut T, VectorError> {
+    try_allocate_in(&Global, capacity)
+}
+
+// Frees a buffer previously returned by `try_allocate` with the same capacity
+pub(crate) unsafe fn deallocate<T>(data: *mut T, capacity: usize) {
+    deallocate_in(&Global, data, capacity)
+}
+
+// Allocates an uninitialized buffer for `capacity` elements from `alloc`;
+// zero-sized types never touch the allocator and get a dangling, well-aligned pointer
+fn try_allocate_in<T, A: Allocator>(alloc: &A, capacity: usize) -> Result<*mut T, VectorError> {
+    let layout = Layout::array::<T>(capacity).map_err(|_| VectorError::CapacityOverflow)?;
+    if layout.size() == 0 {
+        return Ok(NonNull::dangling().as_ptr());
+    }
+    let data = alloc.allocate(layout) as *mut T;
+    if data.is_null() {
+        return Err(VectorError::AllocFailed { layout });
+    }
+    #[cfg(feature = "stats")]
+    crate::stats::record_allocation(layout.size());
+    Ok(data)
+}
+
+// Frees a buffer previously returned by `try_allocate_in` with the same allocator and capacity
+unsafe fn deallocate_in<T, A: Allocator>(alloc: &A, data: *mut T, capacity: usize) {
+    let layout = Layout::array::<T>(capacity).unwrap();
+    if layout.size() == 0 {
+        return;
+    }
+    #[cfg(feature = "stats")]
+    crate::stats::record_deallocation(layout.size());
+    alloc.deallocate(data as *mut u8, layout);
+}
+
+// Scans over `i32` vectors, done 4 or 8 items at a time where the CPU supports it
+impl<P: GrowthPolicy, A: Allocator> Vector<i32, P, A> {
+    // Returns how many items equal `item`
+    pub fn count(&self, item: &i32) -> usize {
+        simd::count(self.as_slice(), *item)
+    }
+
+    // Checks if any item equals `item`
+    pub fn contains(&self, item: &i32) -> bool {
+        simd::find(self.as_slice(), *item).is_some()
+    }
+
+    // Like `find`, returns the index of the first item equal to `item`
+    pub fn simd_find(&self, item: &i32) -> Option<usize> {
+        simd::find(self.as_slice(), *item)
+    }
+
+    // Like `remove`, removes every item equal to `item`, moving the kept items run by run
+    pub fn simd_remove(&mut self, item: &i32) {
+        self.size = simd::retain_ne(self.as_mut_slice(), *item);
+        self.shrink();
+    }
+
+    // Returns the smallest item, or `None` if the vector is empty; named so it does not collide with
+    // `Ord::min`, which compares two whole vectors
+    pub fn minimum(&self) -> Option<i32> {
+        simd::min(self.as_slice())
+    }
+
+    // Returns the largest item, or `None` if the vector is empty
+    pub fn maximum(&self) -> Option<i32> {
+        simd::max(self.as_slice())
+    }
+
+    // Returns the sum of all items; it is computed as an `i64`, so it cannot overflow below 2^32 items
+    pub fn sum(&self) -> i64 {
+        simd::sum(self.as_slice())
     }
 }
 
-// Implement the `Drop` trait to safely deallocate memory when the `Vector` is dropped
-impl Drop for Vector {
+// `Vector` owns its elements and buffer exclusively, like `Vec` does: the raw pointer is never
+// shared with another `Vector`, and elements are only reached through `&self` or `&mut self`.
+// Moving a vector to another thread therefore moves its elements, policy and allocator (`T: Send`,
+// `P: Send`, `A: Send`), and sharing `&Vector` only hands out `&T`, `&P` and `&A` (`T: Sync`,
+// `P: Sync`, `A: Sync`).
+unsafe impl<T: Send, P: Send, A: Allocator + Send> Send for Vector<T, P, A> {}
+unsafe impl<T: Sync, P: Sync, A: Allocator + Sync> Sync for Vector<T, P, A> {}
+
+// Implement the `Drop` trait to drop the remaining elements and then deallocate memory when the `Vector` is dropped
+impl<T, P, A: Allocator> Drop for Vector<T, P, A> {
     fn drop(&mut self) {
-        unsafe { dealloc(self.data as *mut u8, Layout::array::<i32>(self.capacity).unwrap()) };
+        unsafe {
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(self.data, self.size));
+            deallocate_in(&self.alloc, self.data, self.capacity);
+        }
+    }
+}
+
+// Dereference to a slice so that `sort`, `binary_search`, `chunks`, `windows` and friends work directly
+impl<T, P: GrowthPolicy, A: Allocator> Deref for Vector<T, P, A> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> DerefMut for Vector<T, P, A> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+// Index by position (`vec[2]`) or by range (`vec[1..3]`), panicking when out of bounds like a slice does
+impl<T, P: GrowthPolicy, A: Allocator, I: SliceIndex<[T]>> Index<I> for Vector<T, P, A> {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &I::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<T, P: GrowthPolicy, A: Allocator, I: SliceIndex<[T]>> IndexMut<I> for Vector<T, P, A> {
+    fn index_mut(&mut self, index: I) -> &mut I::Output {
+        &mut self.as_mut_slice()[index]
+    }
+}
+
+// Builds a `Vector` from a list of items or from an item and a count, like `vec!`:
+// `vector![]`, `vector![1, 2, 3]` or `vector![0; n]`
+#[macro_export]
+macro_rules! vector {
+    () => {
+        $crate::vector::Vector::new(0)
+    };
+    ($item:expr; $n:expr) => {
+        $crate::vector::Vector::from_elem($item, $n)
+    };
+    ($($item:expr),+ $(,)?) => {
+        $crate::vector::Vector::from([$($item),+])
+    };
+}
+
+// Clone the elements into a new buffer that uses a copy of the policy and of the allocator
+impl<T: Clone, P: GrowthPolicy + Clone, A: Allocator + Clone> Clone for Vector<T, P, A> {
+    fn clone(&self) -> Self {
+        let mut vec = Vector::with_policy_in(self.size, self.policy.clone(), self.alloc.clone());
+        vec.extend_from_slice(self);
+        vec
+    }
+}
+
+impl<T: fmt::Debug, P: GrowthPolicy, A: Allocator> fmt::Debug for Vector<T, P, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+// Display the elements as a bracketed, comma-separated list, e.g. `[1, 2, 3]`
+impl<T: fmt::Display, P: GrowthPolicy, A: Allocator> fmt::Display for Vector<T, P, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (i, item) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", item)?;
+        }
+        write!(f, "]")
+    }
+}
+
+impl<T, P: GrowthPolicy + Default, A: Allocator + Default> Default for Vector<T, P, A> {
+    fn default() -> Self {
+        Vector::with_policy_in(0, P::default(), A::default())
+    }
+}
+
+// Comparisons, ordering and hashing look at the elements only, never at capacity, policy or allocator
+impl<T, U, P, Q, A, B> PartialEq<Vector<U, Q, B>> for Vector<T, P, A>
+where
+    T: PartialEq<U>,
+    P: GrowthPolicy,
+    Q: GrowthPolicy,
+    A: Allocator,
+    B: Allocator,
+{
+    fn eq(&self, other: &Vector<U, Q, B>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: PartialEq<U>, U, P: GrowthPolicy, A: Allocator> PartialEq<[U]> for Vector<T, P, A> {
+    fn eq(&self, other: &[U]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<T: PartialEq<U>, U, P: GrowthPolicy, A: Allocator> PartialEq<&[U]> for Vector<T, P, A> {
+    fn eq(&self, other: &&[U]) -> bool {
+        self.as_slice() == *other
+    }
+}
+
+impl<T: PartialEq<U>, U, P: GrowthPolicy, A: Allocator, const N: usize> PartialEq<[U; N]> for Vector<T, P, A> {
+    fn eq(&self, other: &[U; N]) -> bool {
+        self.as_slice() == other
+    }
+}
+
+impl<T: PartialEq<U>, U, P: GrowthPolicy, A: Allocator> PartialEq<Vec<U>> for Vector<T, P, A> {
+    fn eq(&self, other: &Vec<U>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Eq, P: GrowthPolicy, A: Allocator> Eq for Vector<T, P, A> {}
+
+impl<T: PartialOrd, P: GrowthPolicy, A: Allocator> PartialOrd for Vector<T, P, A> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.as_slice().partial_cmp(other.as_slice())
+    }
+}
+
+impl<T: Ord, P: GrowthPolicy, A: Allocator> Ord for Vector<T, P, A> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_slice().cmp(other.as_slice())
+    }
+}
+
+impl<T: Hash, P: GrowthPolicy, A: Allocator> Hash for Vector<T, P, A> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_slice().hash(state);
+    }
+}
+
+// Conversions from arrays, slices and `Vec`, each allocating once
+impl<T, const N: usize> From<[T; N]> for Vector<T> {
+    fn from(items: [T; N]) -> Self {
+        items.into_iter().collect()
+    }
+}
+
+impl<T: Clone> From<&[T]> for Vector<T> {
+    fn from(items: &[T]) -> Self {
+        let mut vec = Vector::with_capacity(items.len());
+        vec.extend_from_slice(items);
+        vec
+    }
+}
+
+impl<T> From<Vec<T>> for Vector<T> {
+    fn from(items: Vec<T>) -> Self {
+        items.into_iter().collect()
+    }
+}
+
+// Conversions into `Vec` and into arrays of the exact length
+impl<T, P, A: Allocator> From<Vector<T, P, A>> for Vec<T> {
+    fn from(vec: Vector<T, P, A>) -> Self {
+        vec.into_iter().collect()
+    }
+}
+
+impl<T, P, A: Allocator, const N: usize> TryFrom<Vector<T, P, A>> for [T; N] {
+    type Error = Vector<T, P, A>;
+
+    // Moves the elements into an array, or hands the vector back if its size is not `N`
+    fn try_from(mut vec: Vector<T, P, A>) -> Result<Self, Vector<T, P, A>> {
+        if vec.size != N {
+            return Err(vec);
+        }
+        // The elements now belong to the array; the vector only frees its buffer
+        vec.size = 0;
+        Ok(unsafe { ptr::read(vec.data as *const [T; N]) })
+    }
+}
+
+// Iterator returned by `Vector::drain`, yields the removed elements by value
+pub struct Drain<'a, T, P: GrowthPolicy = Doubling, A: Allocator = Global> {
+    vec: &'a mut Vector<T, P, A>, // Vector being drained, its size covers only the leading elements
+    start: usize,                 // Index the trailing elements move to once the drain is dropped
+    next: usize,                  // Index of the next element yielded from the front
+    end: usize,                   // One past the index of the next element yielded from the back
+    tail: usize,                  // Index of the first trailing element after the drained range
+    tail_len: usize,              // Number of trailing elements after the drained range
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> Iterator for Drain<'_, T, P, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.next == self.end {
+            return None;
+        }
+        let value = unsafe { ptr::read(self.vec.data.add(self.next)) };
+        self.next += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> DoubleEndedIterator for Drain<'_, T, P, A> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.next == self.end {
+            return None;
+        }
+        self.end -= 1;
+        Some(unsafe { ptr::read(self.vec.data.add(self.end)) })
+    }
+}
+
+impl<T, P: GrowthPolicy, A: Allocator> ExactSizeIterator for Drain<'_, T, P, A> {}
+
+impl<T, P: GrowthPolicy, A: Allocator> FusedIterator for Drain<'_, T, P, A> {}
+
+// Drop the elements that were never yielded, move the trailing elements left in one pass and
+// shrink like `pop` does
+impl<T, P: GrowthPolicy, A: Allocator> Drop for Drain<'_, T, P, A> {
+    fn drop(&mut self) {
+        unsafe {
+            let data = self.vec.data;
+            let remaining = ptr::slice_from_raw_parts_mut(data.add(self.next), self.end - self.next);
+            self.next = self.end;
+            ptr::drop_in_place(remaining);
+
+            ptr::copy(data.add(self.tail), data.add(self.start), self.tail_len);
+        }
+        self.vec.size = self.start + self.tail_len;
+        self.vec.shrink();
+    }
+}
+
+// Owning iterator returned by `Vector::into_iter`, yields the elements by value
+pub struct IntoIter<T, A: Allocator = Global> {
+    data: *mut T,    // Buffer taken over from the vector
+    capacity: usize, // Capacity of the buffer, needed to deallocate it
+    start: usize,    // Index of the next element yielded from the front
+    end: usize,      // One past the index of the next element yielded from the back
+    alloc: A,        // Allocator the buffer is given back to
+    _marker: PhantomData<T>,
+}
+
+// The iterator owns the remaining elements exclusively, just like the vector it came from
+unsafe impl<T: Send, A: Allocator + Send> Send for IntoIter<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for IntoIter<T, A> {}
+
+impl<T, A: Allocator> Iterator for IntoIter<T, A> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        // Move the front element out; it is no longer part of the live range
+        let value = unsafe { ptr::read(self.data.add(self.start)) };
+        self.start += 1;
+        Some(value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.end - self.start;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<T, A: Allocator> DoubleEndedIterator for IntoIter<T, A> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start == self.end {
+            return None;
+        }
+        // Move the back element out; it is no longer part of the live range
+        self.end -= 1;
+        Some(unsafe { ptr::read(self.data.add(self.end)) })
+    }
+}
+
+impl<T, A: Allocator> ExactSizeIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> FusedIterator for IntoIter<T, A> {}
+
+// Drop the elements that were never yielded, then free the buffer
+impl<T, A: Allocator> Drop for IntoIter<T, A> {
+    fn drop(&mut self) {
+        unsafe {
+            let remaining = self.data.add(self.start);
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(remaining, self.end - self.start));
+            deallocate_in(&self.alloc, self.data, self.capacity);
+        }
+    }
+}
+
+impl<T, P, A: Allocator> IntoIterator for Vector<T, P, A> {
+    type Item = T;
+    type IntoIter = IntoIter<T, A>;
+
+    // Hands the buffer and its allocator over to the iterator without dropping any elements
+    fn into_iter(self) -> IntoIter<T, A> {
+        let mut vec = ManuallyDrop::new(self);
+        // The policy is the only field not handed over
+        unsafe { ptr::drop_in_place(&mut vec.policy) };
+        IntoIter {
+            data: vec.data,
+            capacity: vec.capacity,
+            start: 0,
+            end: vec.size,
+            alloc: unsafe { ptr::read(&vec.alloc) },
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, T, P: GrowthPolicy, A: Allocator> IntoIterator for &'a Vector<T, P, A> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    fn into_iter(self) -> slice::Iter<'a, T> {
+        self.iter()
+    }
+}
+
+impl<'a, T, P: GrowthPolicy, A: Allocator> IntoIterator for &'a mut Vector<T, P, A> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    fn into_iter(self) -> slice::IterMut<'a, T> {
+        self.iter_mut()
+    }
+}
+
+// Builds a vector from an iterator, sizing the buffer from its lower size hint up front
+impl<T, P: GrowthPolicy + Default, A: Allocator + Default> FromIterator<T> for Vector<T, P, A> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let mut vec = Vector::with_policy_in(iter.size_hint().0, P::default(), A::default());
+        vec.extend(iter);
+        vec
+    }
+}
+
+// Appends every item of an iterator, reserving room for its lower size hint once instead of
+// doubling repeatedly through `push`
+impl<T, P: GrowthPolicy, A: Allocator> Extend<T> for Vector<T, P, A> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+impl<'a, T: Copy + 'a, P: GrowthPolicy, A: Allocator> Extend<&'a T> for Vector<T, P, A> {
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied());
     }
 }
 
@@ -193,43 +1173,840 @@ impl Drop for Vector {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::allocator::{BumpAllocator, CountingAllocator};
+    use crate::growth::{FixedIncrement, Hysteresis, NeverShrink, OneAndHalf};
+    use std::alloc::{GlobalAlloc, System};
+    use std::cell::Cell;
+
+    // Test allocator that forwards to the system allocator, but can be told to fail every
+    // allocation made by the current thread
+    struct FailingAllocator;
+
+    thread_local! {
+        static FAIL_ALLOCATIONS: Cell<bool> = const { Cell::new(false) };
+    }
+
+    unsafe impl GlobalAlloc for FailingAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            if FAIL_ALLOCATIONS.try_with(Cell::get).unwrap_or(false) {
+                return ptr::null_mut();
+            }
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: FailingAllocator = FailingAllocator;
+
+    // Runs `f` while every allocation on this thread fails; assertions belong outside of `f`
+    fn without_memory<R>(f: impl FnOnce() -> R) -> R {
+        FAIL_ALLOCATIONS.with(|fail| fail.set(true));
+        let result = f();
+        FAIL_ALLOCATIONS.with(|fail| fail.set(false));
+        result
+    }
+
+    // Allocator that forwards to the global allocator until its budget of allocations is used up
+    struct Limited {
+        budget: Cell<usize>,
+    }
+
+    unsafe impl Allocator for Limited {
+        fn allocate(&self, layout: Layout) -> *mut u8 {
+            if self.budget.get() == 0 {
+                return ptr::null_mut();
+            }
+            self.budget.set(self.budget.get() - 1);
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    // `Vector::new` and friends for any allocator that can be built with `Default`, so that the
+    // suite below reads the same whichever allocator it runs on
+    trait NewIn: Sized {
+        fn new(initial_capacity: usize) -> Self;
+        fn with_capacity(capacity: usize) -> Self;
+        fn try_with_capacity(initial_capacity: usize) -> Result<Self, VectorError>;
+    }
+
+    impl<T, A: Allocator + Default> NewIn for Vector<T, Doubling, A> {
+        fn new(initial_capacity: usize) -> Self {
+            Vector::new_in(initial_capacity, A::default())
+        }
+
+        fn with_capacity(capacity: usize) -> Self {
+            Vector::with_capacity_in(capacity, A::default())
+        }
+
+        fn try_with_capacity(initial_capacity: usize) -> Result<Self, VectorError> {
+            Vector::try_with_capacity_in(initial_capacity, A::default())
+        }
+    }
+
+    trait WithPolicyIn<P> {
+        fn with_policy(initial_capacity: usize, policy: P) -> Self;
+    }
+
+    impl<T, P: GrowthPolicy, A: Allocator + Default> WithPolicyIn<P> for Vector<T, P, A> {
+        fn with_policy(initial_capacity: usize, policy: P) -> Self {
+            Vector::with_policy_in(initial_capacity, policy, A::default())
+        }
+    }
+
+    // `vector!` for whichever `Vector` type is in scope where it is used
+    macro_rules! vector_in {
+        () => {
+            <Vector<_>>::new(0)
+        };
+        ($($item:expr),+ $(,)?) => {
+            <Vector<_>>::from_iter([$($item),+])
+        };
+    }
+
+    // The tests that do not depend on the allocator, run against vectors taking their buffers from `$alloc`
+    macro_rules! vector_test_suite {
+        ($name:ident, $alloc:ty) => {
+            mod $name {
+                use super::*;
+
+                type Vector<T, P = Doubling> = super::Vector<T, P, $alloc>;
+
+                #[test]
+                fn test_vector_operations() {
+                    let mut vec = Vector::new(0);
+
+                    // Test initial state of the vector
+                    assert_eq!(vec.size(), 0);
+                    assert_eq!(vec.capacity(), 16); // Default capacity should be 16
+                    assert!(vec.is_empty());
+
+                    // Test pushing elements
+                    vec.push(10);
+                    assert_eq!(vec.at(0), 10);
+                    assert_eq!(vec.size(), 1);
+
+                    // Test inserting elements
+                    vec.push(20);
+                    vec.insert(1, 15); // Insert 15 at index 1
+                    assert_eq!(vec.at(1), 15);
+
+                    // Test prepending an element
+                    vec.prepend(5); // Insert 5 at the beginning
+                    assert_eq!(vec.at(0), 5);
+
+                    // Test popping an element
+                    let popped_value = vec.pop().unwrap();
+                    assert_eq!(popped_value, 20); // Last element should be 20
+
+                    // Test deleting an element
+                    vec.delete(0); // Delete the first element
+                    assert_eq!(vec.find(&15), Some(1));
+
+                    // Test removing an element by value
+                    vec.remove(&15);
+                    assert_eq!(vec.find(&15), None); // `15` should no longer exist
+
+                    // After all operations, only one element should remain
+                    assert_eq!(vec.size(), 1);
+                }
+
+                #[test]
+                fn test_non_copy_elements() {
+                    let mut vec: Vector<String> = Vector::new(0);
+
+                    // Push enough strings to force several resizes
+                    for i in 0..40 {
+                        vec.push(i.to_string());
+                    }
+                    assert_eq!(vec.size(), 40);
+                    assert_eq!(vec.capacity(), 64);
+                    assert_eq!(vec.at(39), "39");
+
+                    // Insert and delete move the owned strings rather than copying them
+                    vec.insert(0, String::from("first"));
+                    assert_eq!(vec.at(0), "first");
+                    assert_eq!(vec.at(1), "0");
+                    vec.delete(0);
+                    assert_eq!(vec.at(0), "0");
+
+                    // Remove and find compare by reference
+                    vec.push(String::from("7"));
+                    vec.remove(&String::from("7"));
+                    assert_eq!(vec.find(&String::from("7")), None);
+                    assert_eq!(vec.size(), 39);
+
+                    // Popping down to a quarter of the capacity shrinks the buffer
+                    while vec.size() > 10 {
+                        vec.pop();
+                    }
+                    assert_eq!(vec.capacity(), 32);
+                    assert_eq!(vec.pop().as_deref(), Some("10"));
+                }
+
+                #[test]
+                fn test_drops_every_element_exactly_once() {
+                    use std::rc::Rc;
+
+                    let tracker = Rc::new(());
+                    let mut vec = Vector::new(0);
+                    for _ in 0..20 {
+                        vec.push(Rc::clone(&tracker));
+                    }
+                    assert_eq!(Rc::strong_count(&tracker), 21);
+
+                    // Deleted and popped elements are dropped immediately
+                    vec.delete(3);
+                    drop(vec.pop());
+                    assert_eq!(Rc::strong_count(&tracker), 19);
+
+                    // Dropping the vector drops the remaining elements
+                    drop(vec);
+                    assert_eq!(Rc::strong_count(&tracker), 1);
+                }
+
+                #[test]
+                fn test_zero_sized_elements() {
+                    let mut vec = Vector::new(0);
+                    for _ in 0..100 {
+                        vec.push(());
+                    }
+                    assert_eq!(vec.size(), 100);
+                    assert_eq!(vec.pop(), Some(()));
+                    assert_eq!(vec.find(&()), Some(0));
+                }
+
+                #[test]
+                fn test_borrowing_iterators() {
+                    let mut vec = Vector::new(0);
+                    for i in 1..=5 {
+                        vec.push(i);
+                    }
+
+                    // Shared iteration visits the elements in order
+                    let collected: Vec<i32> = vec.iter().copied().collect();
+                    assert_eq!(collected, [1, 2, 3, 4, 5]);
+                    assert_eq!(vec.iter().next_back(), Some(&5));
+                    assert_eq!(vec.iter().len(), 5);
+
+                    // Mutable iteration updates the elements in place
+                    for item in &mut vec {
+                        *item *= 10;
+                    }
+                    let mut sum = 0;
+                    for item in &vec {
+                        sum += item;
+                    }
+                    assert_eq!(sum, 150);
+                }
+
+                #[test]
+                fn test_owning_iterator() {
+                    let vec: Vector<String> = ["a", "b", "c", "d"].iter().map(|s| s.to_string()).collect();
+                    let mut iter = vec.into_iter();
+                    assert_eq!(iter.len(), 4);
+                    assert_eq!(iter.next().as_deref(), Some("a"));
+                    assert_eq!(iter.next_back().as_deref(), Some("d"));
+                    assert_eq!(iter.len(), 2);
+
+                    // The remaining elements are dropped together with the iterator
+                    use std::rc::Rc;
+                    let tracker = Rc::new(());
+                    let vec: Vector<Rc<()>> = (0..10).map(|_| Rc::clone(&tracker)).collect();
+                    let mut iter = vec.into_iter();
+                    iter.next();
+                    drop(iter);
+                    assert_eq!(Rc::strong_count(&tracker), 1);
+                }
+
+                #[test]
+                fn test_collect_and_extend_reserve_once() {
+                    // Collecting sizes the buffer from the iterator up front
+                    let vec: Vector<u64> = (0..100).collect();
+                    assert_eq!(vec.size(), 100);
+                    assert_eq!(vec.capacity(), 128);
+
+                    // Extending grows straight to the next power of two that fits
+                    let mut vec: Vector<i32> = Vector::new(0);
+                    vec.extend(0..40);
+                    assert_eq!(vec.capacity(), 64);
+                    vec.extend(&[40, 41]);
+                    assert_eq!(vec.size(), 42);
+                    assert_eq!(vec.at(41), 41);
+
+                    // Iterators with an unknown length still work through `push`
+                    vec.extend((0..100).filter(|i| i % 2 == 0));
+                    assert_eq!(vec.size(), 92);
+                    assert_eq!(vec.capacity(), 128);
+                }
+
+                #[test]
+                fn test_slice_access() {
+                    let mut vec: Vector<i32> = [5, 3, 9, 1, 7].iter().copied().collect();
+                    assert_eq!(vec.as_slice(), &[5, 3, 9, 1, 7]);
+
+                    // Single positions and ranges
+                    assert_eq!(vec[2], 9);
+                    assert_eq!(&vec[1..3], &[3, 9]);
+                    assert_eq!(&vec[3..], &[1, 7]);
+                    vec[0] = 6;
+                    vec[3..].copy_from_slice(&[2, 8]);
+                    assert_eq!(vec.as_slice(), &[6, 3, 9, 2, 8]);
+
+                    // Slice methods are available through `Deref`/`DerefMut`
+                    vec.sort();
+                    assert_eq!(vec.as_slice(), &[2, 3, 6, 8, 9]);
+                    assert_eq!(vec.binary_search(&8), Ok(3));
+                    assert_eq!(vec.windows(2).count(), 4);
+                    assert_eq!(vec.chunks(2).last(), Some(&[9][..]));
+                    vec.as_mut_slice().reverse();
+                    assert_eq!(vec.first(), Some(&9));
+
+                    // Functions taking slices accept a borrowed vector
+                    fn total(values: &[i32]) -> i32 {
+                        values.iter().sum()
+                    }
+                    assert_eq!(total(&vec), 28);
+                }
+
+                #[test]
+                #[should_panic]
+                fn test_index_out_of_bounds() {
+                    let vec: Vector<i32> = Vector::new(0);
+                    let _ = vec[0];
+                }
+
+                #[test]
+                fn test_non_panicking_api() {
+                    let mut vec: Vector<i32> = Vector::new(0);
+                    assert_eq!(vec.get(0), None);
+                    assert_eq!(vec.try_delete(0), Err(VectorError::IndexOutOfBounds { index: 0, len: 0 }));
+                    assert_eq!(vec.try_insert(1, 5), Err(VectorError::IndexOutOfBounds { index: 1, len: 0 }));
+
+                    assert_eq!(vec.try_push(1), Ok(()));
+                    assert_eq!(vec.try_push(3), Ok(()));
+                    assert_eq!(vec.try_insert(1, 2), Ok(()));
+                    assert_eq!(vec.as_slice(), &[1, 2, 3]);
+
+                    // A failed operation leaves the vector untouched
+                    assert!(vec.try_insert(3, 4).is_err());
+                    assert_eq!(vec.as_slice(), &[1, 2, 3]);
+
+                    if let Some(item) = vec.get_mut(2) {
+                        *item = 30;
+                    }
+                    assert_eq!(vec.get(2), Some(&30));
+                    assert_eq!(vec.try_delete(0), Ok(1));
+                    assert_eq!(vec.find(&30), Some(1));
+                    assert_eq!(vec.find(&1), None);
+                }
+
+                #[test]
+                #[should_panic(expected = "Index out of bounds")]
+                fn test_delete_out_of_bounds_panics() {
+                    let mut vec: Vector<i32> = Vector::new(0);
+                    vec.delete(0);
+                }
+
+                #[test]
+                fn test_capacity_overflow_is_reported() {
+                    assert_eq!(Vector::<i32>::try_with_capacity(usize::MAX).err(), Some(VectorError::CapacityOverflow));
+                    assert_eq!(Vector::<i32>::try_with_capacity(1 << 62).err(), Some(VectorError::CapacityOverflow));
+
+                    let mut vec: Vector<i32> = Vector::new(0);
+                    vec.push(1);
+                    assert_eq!(vec.try_reserve(usize::MAX), Err(VectorError::CapacityOverflow));
+                    assert_eq!(vec.try_reserve(isize::MAX as usize), Err(VectorError::CapacityOverflow));
+                    assert_eq!(vec.try_reserve(100), Ok(()));
+                    assert_eq!(vec.capacity(), 128);
+                    assert_eq!(vec.as_slice(), &[1]);
+                }
+
+                #[test]
+                #[should_panic(expected = "Capacity overflow")]
+                fn test_reserve_overflow_panics() {
+                    let mut vec: Vector<u64> = Vector::new(0);
+                    vec.reserve(usize::MAX);
+                }
+
+                #[test]
+                fn test_growth_policy_applies_to_every_operation() {
+                    // Growth through push and insert
+                    let mut vec = Vector::with_policy(0, FixedIncrement::new(8));
+                    for i in 0..17 {
+                        vec.push(i);
+                    }
+                    assert_eq!(vec.capacity(), 24);
+                    for i in 0..8 {
+                        vec.insert(0, i);
+                    }
+                    assert_eq!(vec.capacity(), 32);
+
+                    // Shrinking through pop and delete, once two steps are unused
+                    for _ in 0..9 {
+                        vec.pop();
+                    }
+                    assert_eq!(vec.capacity(), 24);
+                    while vec.size() > 1 {
+                        vec.delete(0);
+                    }
+                    assert_eq!(vec.capacity(), 16);
+
+                    let mut vec = Vector::with_policy(0, OneAndHalf);
+                    vec.extend(0..17);
+                    assert_eq!(vec.capacity(), 24);
+                }
+
+                #[test]
+                fn test_delete_shrinks_like_pop() {
+                    let mut popped: Vector<i32> = (0..64).collect();
+                    let mut deleted: Vector<i32> = (0..64).collect();
+                    while popped.size() > 4 {
+                        popped.pop();
+                        deleted.delete(0);
+                        assert_eq!(popped.capacity(), deleted.capacity());
+                    }
+                    assert_eq!(deleted.capacity(), 16);
+                }
+
+                #[test]
+                fn test_never_shrink_and_hysteresis() {
+                    let mut vec = Vector::with_policy(0, NeverShrink(Doubling));
+                    vec.extend(0..100);
+                    while vec.pop().is_some() {}
+                    assert_eq!(vec.capacity(), 128);
+
+                    // Alternating pushes and pops around the low-water mark do not reallocate
+                    let mut vec = Vector::with_policy(0, Hysteresis::default());
+                    vec.extend(0..100);
+                    while vec.size() > 32 {
+                        vec.pop();
+                    }
+                    assert_eq!(vec.capacity(), 128);
+                    vec.pop();
+                    assert_eq!(vec.capacity(), 62);
+                    for i in 0..10 {
+                        vec.push(i);
+                        vec.pop();
+                    }
+                    assert_eq!(vec.capacity(), 62);
+                }
+
+                #[test]
+                fn test_with_capacity_rule() {
+                    assert_eq!(Vector::<i32>::with_capacity(0).capacity(), 16);
+                    assert_eq!(Vector::<i32>::with_capacity(5).capacity(), 16);
+                    assert_eq!(Vector::<i32>::with_capacity(17).capacity(), 32);
+                    assert_eq!(Vector::<i32>::with_capacity(128).capacity(), 128);
+                    assert_eq!(Vector::<i32>::new(5).capacity(), 16);
+                }
+
+                #[test]
+                fn test_reserve_and_shrink() {
+                    let mut vec: Vector<i32> = Vector::new(0);
+                    vec.extend(0..10);
+
+                    // Reserving rounds up through the growth policy, reserving exactly does not
+                    vec.reserve(10);
+                    assert_eq!(vec.capacity(), 32);
+                    vec.reserve(20);
+                    assert_eq!(vec.capacity(), 32);
+                    vec.reserve_exact(50);
+                    assert_eq!(vec.capacity(), 60);
+
+                    // Shrinking never drops elements
+                    vec.shrink_to(20);
+                    assert_eq!(vec.capacity(), 20);
+                    vec.shrink_to(5);
+                    assert_eq!(vec.capacity(), 10);
+                    vec.shrink_to_fit();
+                    assert_eq!(vec.capacity(), 10);
+                    assert!(vec.iter().copied().eq(0..10));
+
+                    // An exactly sized buffer grows again on the next push
+                    vec.push(10);
+                    assert_eq!(vec.capacity(), 20);
+
+                    // Even an empty buffer can be released and reused
+                    vec.clear();
+                    vec.shrink_to_fit();
+                    assert_eq!(vec.capacity(), 0);
+                    vec.push(1);
+                    assert_eq!(vec.as_slice(), &[1]);
+                }
+
+                #[test]
+                fn test_truncate_and_clear() {
+                    use std::rc::Rc;
+
+                    let tracker = Rc::new(());
+                    let mut vec: Vector<Rc<()>> = (0..100).map(|_| Rc::clone(&tracker)).collect();
+                    assert_eq!(vec.capacity(), 128);
+
+                    // Truncating drops the tail and shrinks as far as popping one by one would
+                    vec.truncate(40);
+                    assert_eq!(vec.size(), 40);
+                    assert_eq!(Rc::strong_count(&tracker), 41);
+                    assert_eq!(vec.capacity(), 128);
+                    vec.truncate(10);
+                    assert_eq!(vec.capacity(), 32);
+                    vec.truncate(20);
+                    assert_eq!(vec.size(), 10);
+
+                    vec.clear();
+                    assert!(vec.is_empty());
+                    assert_eq!(vec.capacity(), 16);
+                    assert_eq!(Rc::strong_count(&tracker), 1);
+                }
+
+                #[test]
+                fn test_shrinking_matches_after_pop_delete_and_remove() {
+                    let mut popped: Vector<i32> = (0..100).collect();
+                    let mut deleted: Vector<i32> = (0..100).collect();
+                    let mut removed: Vector<i32> = (0..100).map(|i| if i < 90 { 0 } else { i }).collect();
+                    for _ in 0..90 {
+                        popped.pop();
+                        deleted.delete(0);
+                    }
+                    removed.remove(&0);
+                    assert_eq!(removed.size(), 10);
+                    assert_eq!(popped.capacity(), 32);
+                    assert_eq!(deleted.capacity(), 32);
+                    assert_eq!(removed.capacity(), 32);
+                }
+
+                #[test]
+                fn test_extend_and_insert_slices() {
+                    let mut vec: Vector<String> = Vector::new(0);
+                    let words: Vec<String> = ["b", "c", "f"].iter().map(|s| s.to_string()).collect();
+                    vec.extend_from_slice(&words);
+                    vec.insert_slice(0, &[String::from("a")]);
+                    vec.insert_slice(3, &[String::from("d"), String::from("e")]);
+                    vec.insert_slice(6, &[String::from("g")]);
+                    assert_eq!(vec.as_slice(), &["a", "b", "c", "d", "e", "f", "g"]);
+
+                    // Large slices reallocate once, straight to the final capacity
+                    let mut vec: Vector<i32> = Vector::new(0);
+                    vec.push(0);
+                    vec.insert_slice(0, &[1; 100]);
+                    assert_eq!(vec.size(), 101);
+                    assert_eq!(vec.capacity(), 128);
+                    assert_eq!(vec[100], 0);
+                }
+
+                #[test]
+                #[should_panic(expected = "Index out of bounds")]
+                fn test_insert_slice_out_of_bounds() {
+                    let mut vec: Vector<i32> = Vector::new(0);
+                    vec.insert_slice(1, &[1]);
+                }
+
+                #[test]
+                fn test_drain() {
+                    let mut vec: Vector<i32> = (0..10).collect();
+                    let drained: Vec<i32> = vec.drain(2..5).collect();
+                    assert_eq!(drained, [2, 3, 4]);
+                    assert_eq!(vec.as_slice(), &[0, 1, 5, 6, 7, 8, 9]);
+
+                    // Consuming from both ends, or not at all, still closes the gap
+                    let mut drain = vec.drain(1..=4);
+                    assert_eq!(drain.next(), Some(1));
+                    assert_eq!(drain.next_back(), Some(7));
+                    drop(drain);
+                    assert_eq!(vec.as_slice(), &[0, 8, 9]);
+                    vec.drain(..);
+                    assert!(vec.is_empty());
+
+                    // Dropping the drain drops the elements it did not yield
+                    use std::rc::Rc;
+                    let tracker = Rc::new(());
+                    let mut vec: Vector<Rc<()>> = (0..100).map(|_| Rc::clone(&tracker)).collect();
+                    vec.drain(10..);
+                    assert_eq!(Rc::strong_count(&tracker), 11);
+                    assert_eq!(vec.capacity(), 32);
+                }
+
+                #[test]
+                fn test_splice() {
+                    let mut vec: Vector<i32> = (0..6).collect();
+                    let removed = vec.splice(1..3, [10, 20, 30]);
+                    assert_eq!(removed.as_slice(), &[1, 2]);
+                    assert_eq!(vec.as_slice(), &[0, 10, 20, 30, 3, 4, 5]);
+
+                    // Shorter replacements and pure insertions
+                    let removed = vec.splice(1..4, [7]);
+                    assert_eq!(removed.as_slice(), &[10, 20, 30]);
+                    assert_eq!(vec.as_slice(), &[0, 7, 3, 4, 5]);
+                    vec.splice(5..5, [6, 8]);
+                    assert_eq!(vec.as_slice(), &[0, 7, 3, 4, 5, 6, 8]);
+
+                    // Growing past the capacity reallocates once
+                    let removed = vec.splice(..1, 100..140);
+                    assert_eq!(removed.as_slice(), &[0]);
+                    assert_eq!(vec.size(), 46);
+                    assert_eq!(vec.capacity(), 64);
+                    assert_eq!(vec[40], 7);
+                }
+
+                #[test]
+                fn test_splice_with_short_iterator() {
+                    // An iterator that yields fewer items than it promised leaves no gap behind
+                    struct Liar(std::ops::Range<i32>);
+                    impl Iterator for Liar {
+                        type Item = i32;
+                        fn next(&mut self) -> Option<i32> {
+                            self.0.next()
+                        }
+                        fn size_hint(&self) -> (usize, Option<usize>) {
+                            (5, Some(5))
+                        }
+                    }
+                    impl ExactSizeIterator for Liar {}
+
+                    let mut vec: Vector<i32> = (0..4).collect();
+                    vec.splice(1..2, Liar(10..12));
+                    assert_eq!(vec.as_slice(), &[0, 10, 11, 2, 3]);
+                }
+
+                #[test]
+                fn test_retain_and_dedup() {
+                    let mut vec: Vector<i32> = (0..20).collect();
+                    vec.retain(|x| x % 3 == 0);
+                    assert_eq!(vec.as_slice(), &[0, 3, 6, 9, 12, 15, 18]);
+
+                    let mut vec: Vector<i32> = [1, 1, 2, 3, 3, 3, 1, 4, 4].iter().copied().collect();
+                    vec.dedup();
+                    assert_eq!(vec.as_slice(), &[1, 2, 3, 1, 4]);
+
+                    let mut vec: Vector<i32> = [10, 11, 20, 25, 31, 12].iter().copied().collect();
+                    vec.dedup_by_key(|x| *x / 10);
+                    assert_eq!(vec.as_slice(), &[10, 20, 31, 12]);
+
+                    // Filtering drops exactly the removed elements
+                    use std::rc::Rc;
+                    let tracker = Rc::new(());
+                    let mut vec: Vector<(usize, Rc<()>)> = (0..50).map(|i| (i, Rc::clone(&tracker))).collect();
+                    vec.retain(|(i, _)| i % 10 == 0);
+                    assert_eq!(vec.size(), 5);
+                    assert_eq!(Rc::strong_count(&tracker), 6);
+                }
+
+                #[test]
+                fn test_i32_scans_match_generic_path() {
+                    // `Vector<i64>` takes the element-by-element path, `Vector<i32>` the vectorized one
+                    for len in [0, 1, 7, 8, 9, 33, 1000] {
+                        let values: Vec<i32> = (0..len).map(|i| (i * 7919) % 13 - 6).collect();
+                        let narrow: Vector<i32> = values.iter().copied().collect();
+                        let wide: Vector<i64> = values.iter().map(|&x| x as i64).collect();
+                        for item in -7..8 {
+                            assert_eq!(narrow.find(&item), wide.find(&(item as i64)));
+                            assert_eq!(narrow.simd_find(&item), narrow.find(&item));
+                            assert_eq!(narrow.count(&item), wide.iter().filter(|&&x| x == item as i64).count());
+                            assert_eq!(narrow.contains(&item), wide.find(&(item as i64)).is_some());
+
+                            let mut scalar = narrow.clone();
+                            let mut narrow = narrow.clone();
+                            let mut wide = wide.clone();
+                            scalar.remove(&item);
+                            narrow.simd_remove(&item);
+                            wide.remove(&(item as i64));
+                            assert!(narrow.iter().map(|&x| x as i64).eq(wide.iter().copied()));
+                            assert_eq!(narrow, scalar);
+                            assert_eq!(narrow.capacity(), wide.capacity());
+                        }
+                        assert_eq!(narrow.minimum().map(i64::from), wide.iter().copied().min());
+                        assert_eq!(narrow.maximum().map(i64::from), wide.iter().copied().max());
+                        assert_eq!(narrow.sum(), wide.iter().sum::<i64>());
+                    }
+
+                    let vec = vector_in![i32::MAX, i32::MAX, i32::MIN];
+                    assert_eq!(vec.sum(), i32::MAX as i64 - 1);
+                    assert_eq!((vec.minimum(), vec.maximum()), (Some(i32::MIN), Some(i32::MAX)));
+                }
+
+                #[test]
+                fn test_find_and_remove_borrowed_elements() {
+                    let text = String::from("to be or not to be");
+                    let mut vec: Vector<&str> = text.split(' ').collect();
+                    assert_eq!(vec.find(&"be"), Some(1));
+                    assert_eq!(vec.find(&"maybe"), None);
+                    vec.remove(&"to");
+                    assert_eq!(vec, ["be", "or", "not", "be"]);
+                }
+
+                #[test]
+                // Hashing and ordering ignore the allocator, even one with shared counters inside
+                #[allow(clippy::mutable_key_type)]
+                fn test_standard_traits() {
+                    use std::collections::{BTreeMap, HashSet};
+
+                    let vec = vector_in![3, 1, 2];
+                    let copy = vec.clone();
+                    assert_eq!(vec, copy);
+                    assert_eq!(format!("{:?}", vec), "[3, 1, 2]");
+                    assert_eq!(format!("{}", vector_in!["a", "b"]), "[a, b]");
+                    assert_eq!(Vector::<i32>::default().capacity(), 16);
+
+                    // Ordering is lexicographic, like slices
+                    assert!(vector_in![1, 2] < vector_in![1, 3]);
+                    assert!(vector_in![1, 2] < vector_in![1, 2, 0]);
+                    assert_eq!(vector_in![2].cmp(&vector_in![1, 9]), Ordering::Greater);
+
+                    // Vectors work as keys, regardless of capacity
+                    let mut map = BTreeMap::new();
+                    map.insert(vector_in![2, 0], "b");
+                    map.insert(vector_in![1, 5], "a");
+                    assert_eq!(map.values().copied().collect::<Vec<_>>(), ["a", "b"]);
+                    let mut set = HashSet::new();
+                    set.insert(vector_in![1, 2]);
+                    let mut big: Vector<i32> = Vector::with_capacity(1000);
+                    big.extend([1, 2]);
+                    assert!(set.contains(&big));
+                }
+
+                #[test]
+                fn test_send_and_sync() {
+                    use std::sync::Arc;
+                    use std::thread;
+
+                    fn assert_send_sync<T: Send + Sync>() {}
+                    assert_send_sync::<Vector<String>>();
+                    assert_send_sync::<Vector<i32, Hysteresis>>();
+                    assert_send_sync::<IntoIter<Vec<u8>, $alloc>>();
+
+                    // Move a vector into a thread and get it back
+                    let vec: Vector<String> = (0..100).map(|i| i.to_string()).collect();
+                    let vec = thread::spawn(move || {
+                        let mut vec = vec;
+                        vec.push(String::from("from thread"));
+                        vec
+                    })
+                    .join()
+                    .unwrap();
+                    assert_eq!(vec.size(), 101);
+
+                    // Share one vector between readers
+                    let shared = Arc::new(vec);
+                    let lengths: Vec<usize> = (0..4)
+                        .map(|_| {
+                            let shared = Arc::clone(&shared);
+                            thread::spawn(move || shared.iter().map(String::len).sum())
+                        })
+                        .map(|handle| handle.join().unwrap())
+                        .collect();
+                    assert!(lengths.iter().all(|&len| len == lengths[0]));
+
+                    // Drain an owning iterator on another thread
+                    let vec: Vector<Box<i32>> = (0..10).map(Box::new).collect();
+                    let sum = thread::spawn(move || vec.into_iter().map(|x| *x).sum::<i32>()).join().unwrap();
+                    assert_eq!(sum, 45);
+                }
+            }
+        };
+    }
+
+    vector_test_suite!(global, Global);
+    vector_test_suite!(counting, CountingAllocator);
+    vector_test_suite!(bump, BumpAllocator);
 
     #[test]
-    fn test_vector_operations() {
-        let mut vec = Vector::new(0);
+    fn test_error_messages() {
+        let err = VectorError::IndexOutOfBounds { index: 4, len: 2 };
+        assert_eq!(err.to_string(), "Index out of bounds: the len is 2 but the index is 4");
+        assert_eq!(VectorError::CapacityOverflow.to_string(), "Capacity overflow");
+    }
 
-        // Test initial state of the vector
-        assert_eq!(vec.size(), 0);
-        assert_eq!(vec.capacity(), 16); // Default capacity should be 16
-        assert!(vec.is_empty());
+    #[test]
+    fn test_allocation_failure_is_reported() {
+        // Creating a vector
+        let result = without_memory(|| Vector::<i32>::try_with_capacity(0).map(|_| ()));
+        let layout = Layout::array::<i32>(16).unwrap();
+        assert_eq!(result, Err(VectorError::AllocFailed { layout }));
+
+        // Growing a full vector leaves it untouched
+        let mut vec: Vector<i32> = (0..16).collect();
+        let result = without_memory(|| (vec.try_push(16), vec.try_insert(0, -1), vec.try_reserve(1)));
+        let layout = Layout::array::<i32>(32).unwrap();
+        assert_eq!(result.0, Err(VectorError::AllocFailed { layout }));
+        assert_eq!(result.1, Err(VectorError::AllocFailed { layout }));
+        assert_eq!(result.2, Err(VectorError::AllocFailed { layout }));
+        assert_eq!(vec.size(), 16);
+        assert_eq!(vec.capacity(), 16);
+        assert!(vec.iter().copied().eq(0..16));
+
+        // Shrinking is skipped rather than failing
+        let mut vec: Vector<i32> = (0..64).collect();
+        without_memory(|| {
+            while vec.size() > 1 {
+                vec.pop();
+            }
+        });
+        assert_eq!(vec.capacity(), 64);
+        assert_eq!(vec.as_slice(), &[0]);
+    }
+
+    #[test]
+    fn test_vector_macro() {
+        let empty: Vector<i32> = vector![];
+        assert!(empty.is_empty());
 
-        // Test pushing elements
-        vec.push(10);
-        assert_eq!(vec.at(0), 10);
-        assert_eq!(vec.size(), 1);
+        let vec = vector![1, 2, 3];
+        assert_eq!(vec, [1, 2, 3]);
 
-        // Test inserting elements
-        vec.push(20);
-        vec.insert(1, 15); // Insert 15 at index 1
-        assert_eq!(vec.at(1), 15);
+        let vec = vector![String::from("x"); 3];
+        assert_eq!(vec, ["x", "x", "x"]);
+        let vec: Vector<i32> = vector![7; 0];
+        assert!(vec.is_empty());
+    }
 
-        // Test prepending an element
-        vec.prepend(5); // Insert 5 at the beginning
-        assert_eq!(vec.at(0), 5);
+    #[test]
+    fn test_conversions() {
+        let vec = Vector::from([1, 2, 3]);
+        assert_eq!(vec, vec![1, 2, 3]);
+        let vec = Vector::from(&[4, 5][..]);
+        assert_eq!(vec, &[4, 5][..]);
+        let vec = Vector::from(vec![String::from("a")]);
+        assert_eq!(Vec::from(vec), ["a"]);
 
-        // Test popping an element
-        let popped_value = vec.pop().unwrap();
-        assert_eq!(popped_value, 20); // Last element should be 20
+        // Only vectors of the exact length become arrays
+        let array: [i32; 3] = vector![1, 2, 3].try_into().unwrap();
+        assert_eq!(array, [1, 2, 3]);
+        let result: Result<[i32; 2], _> = vector![1, 2, 3].try_into();
+        assert_eq!(result.unwrap_err(), [1, 2, 3]);
 
-        // Test deleting an element
-        vec.delete(0); // Delete the first element
-        assert_eq!(vec.find(15), 1);
+        // Moving into an array does not drop the elements twice
+        use std::rc::Rc;
+        let tracker = Rc::new(());
+        let array: [Rc<()>; 2] = vector![Rc::clone(&tracker), Rc::clone(&tracker)].try_into().unwrap();
+        assert_eq!(Rc::strong_count(&tracker), 3);
+        drop(array);
+        assert_eq!(Rc::strong_count(&tracker), 1);
+    }
 
-        // Test removing an element by value
-        vec.remove(15);
-        assert_eq!(vec.find(15), -1); // `15` should no longer exist
+    #[test]
+    fn test_allocator_failure_is_reported() {
+        let limited = Limited { budget: Cell::new(1) };
+        let mut vec = Vector::try_with_capacity_in(0, &limited).unwrap();
+        vec.extend(0..16);
+
+        // Growing fails once the allocator runs dry, leaving the vector untouched
+        let layout = Layout::array::<i32>(32).unwrap();
+        assert_eq!(vec.try_push(16), Err(VectorError::AllocFailed { layout }));
+        assert_eq!(vec.capacity(), 16);
+        assert!(vec.iter().copied().eq(0..16));
 
-        // After all operations, only one element should remain
-        assert_eq!(vec.size(), 1);
+        limited.budget.set(1);
+        assert_eq!(vec.try_push(16), Ok(()));
+        assert_eq!(vec.allocator().budget.get(), 0);
+        let layout = Layout::array::<i32>(16).unwrap();
+        let result = Vector::<i32, Doubling, _>::try_with_capacity_in(0, &limited).map(|_| ());
+        assert_eq!(result, Err(VectorError::AllocFailed { layout }));
     }
-}
\ No newline at end of file
+}